@@ -1,8 +1,9 @@
 use clap::{App, Arg};
 use gstreamer as gst;
-use std::net::SocketAddr;
-use switcher::http::Server;
+use std::{net::SocketAddr, path::PathBuf, time::Duration};
+use switcher::http::{auth, Server, TlsConfig};
 use thiserror::Error;
+use tokio::signal::unix::{signal, SignalKind};
 
 #[derive(Debug, Error)]
 enum RTMPSwitcherError {
@@ -11,6 +12,18 @@ enum RTMPSwitcherError {
 
     #[error("invalid listen address `{0}`")]
     InvalidSocketAddr(String),
+
+    #[error("invalid shutdown timeout `{0}`")]
+    InvalidShutdownTimeout(String),
+
+    #[error("invalid credentials: {0}")]
+    InvalidCredentials(String),
+
+    #[error("failed reading auth file `{0}`: {1}")]
+    InvalidAuthFile(String, String),
+
+    #[error("failed reading TLS file `{0}`: {1}")]
+    InvalidTlsFile(String, std::io::Error),
 }
 
 #[tokio::main]
@@ -28,16 +41,115 @@ async fn main() -> eyre::Result<()> {
                 .help("sets the server listen address")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("shutdown-timeout")
+                .long("shutdown-timeout")
+                .value_name("SECONDS")
+                .help("sets how long outputs may take to finish when shutting down")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("token")
+                .long("token")
+                .value_name("ROLE:TOKEN")
+                .help("accepts a bearer token granting a role of viewer, operator or admin")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("user")
+                .long("user")
+                .value_name("ROLE:USERNAME:PASSWORD")
+                .help("accepts a basic auth user granting a role of viewer, operator or admin")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("auth-file")
+                .long("auth-file")
+                .value_name("FILE")
+                .help("reads accepted tokens and users from a JSON file")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("tls-cert")
+                .long("tls-cert")
+                .value_name("FILE")
+                .help("serves the API over HTTPS with this PEM certificate chain")
+                .takes_value(true)
+                .requires("tls-key"),
+        )
+        .arg(
+            Arg::with_name("tls-key")
+                .long("tls-key")
+                .value_name("FILE")
+                .help("sets the PEM private key of the TLS certificate")
+                .takes_value(true)
+                .requires("tls-cert"),
+        )
+        .arg(
+            Arg::with_name("redirect-addr")
+                .long("redirect-addr")
+                .value_name("ADDRESS")
+                .help("redirects plain HTTP requests on this address to HTTPS")
+                .takes_value(true)
+                .requires("tls-cert"),
+        )
         .get_matches();
     let addr: SocketAddr = parse_addr(matches.value_of("addr").unwrap_or({
         eprintln!("using 0.0.0.0:3030 as addr");
         "0.0.0.0:3030"
     }))?;
 
+    let shutdown_timeout = parse_timeout(matches.value_of("shutdown-timeout").unwrap_or("10"))?;
+
+    let mut auth = match matches.value_of("auth-file") {
+        Some(path) => read_auth_file(path)?,
+        None => auth::Config::default(),
+    };
+    for token in matches.values_of("token").into_iter().flatten() {
+        auth.tokens.push(
+            token
+                .parse()
+                .map_err(RTMPSwitcherError::InvalidCredentials)?,
+        );
+    }
+    for user in matches.values_of("user").into_iter().flatten() {
+        auth.users.push(
+            user.parse()
+                .map_err(RTMPSwitcherError::InvalidCredentials)?,
+        );
+    }
+    if !auth.enabled() {
+        eprintln!("no tokens or users given, anyone reaching the server has full access");
+    }
+
     gst::init().map_err(RTMPSwitcherError::FailedInitGstreamer)?;
 
-    let server = Server::new_with_config(addr);
-    server.run().await;
+    let mut terminate = signal(SignalKind::terminate())?;
+    let shutdown = async move {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {},
+            _ = terminate.recv() => {},
+        }
+    };
+
+    let mut server = Server::new_with_config(addr).with_auth(auth);
+    if let (Some(cert), Some(key)) = (matches.value_of("tls-cert"), matches.value_of("tls-key")) {
+        server = server.with_tls(TlsConfig {
+            cert_path: check_tls_file(cert)?,
+            key_path: check_tls_file(key)?,
+            redirect_addr: matches
+                .value_of("redirect-addr")
+                .map(parse_addr)
+                .transpose()?,
+        });
+    } else {
+        eprintln!("no TLS certificate given, credentials and stream keys are sent in cleartext");
+    }
+    server.run_until(shutdown, shutdown_timeout).await;
 
     Ok(())
 }
@@ -47,3 +159,24 @@ fn parse_addr(raw_addr: &str) -> Result<SocketAddr, RTMPSwitcherError> {
         .parse::<SocketAddr>()
         .map_err(|_| RTMPSwitcherError::InvalidSocketAddr(raw_addr.to_string()))
 }
+
+fn parse_timeout(raw_timeout: &str) -> Result<Duration, RTMPSwitcherError> {
+    raw_timeout
+        .parse::<u64>()
+        .map(Duration::from_secs)
+        .map_err(|_| RTMPSwitcherError::InvalidShutdownTimeout(raw_timeout.to_string()))
+}
+
+fn read_auth_file(path: &str) -> Result<auth::Config, RTMPSwitcherError> {
+    std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|raw| serde_json::from_str(&raw).map_err(|e| e.to_string()))
+        .map_err(|e| RTMPSwitcherError::InvalidAuthFile(path.to_string(), e))
+}
+
+/// Makes sure a certificate or key can be read, as the server panics on files it can't read.
+fn check_tls_file(path: &str) -> Result<PathBuf, RTMPSwitcherError> {
+    std::fs::File::open(path)
+        .map_err(|e| RTMPSwitcherError::InvalidTlsFile(path.to_string(), e))?;
+    Ok(PathBuf::from(path))
+}
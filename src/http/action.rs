@@ -0,0 +1,263 @@
+use super::{
+    auth::Role,
+    input::CreateRequest as InputCreateRequest,
+    output::{CreateRequest as OutputCreateRequest, RecordingAction},
+    Error,
+};
+use crate::{secret::SecretUrl, IsoConfig};
+
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, sync::Arc, time::Duration};
+use tokio::sync::Mutex;
+
+/// Interval between alpha updates while crossfading an input in.
+const CROSSFADE_STEP_MS: u64 = 40;
+
+/// Switcher action run against a mixer by rundown cues and macros.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(tag = "action")]
+pub enum Action {
+    /// Sets an input active, fading it in over `crossfade_ms` when non zero.
+    SetActive {
+        input: String,
+        #[serde(default)]
+        crossfade_ms: u64,
+    },
+    AddInput {
+        input: InputCreateRequest,
+    },
+    RemoveInput {
+        input: String,
+    },
+    AddOutput {
+        output: OutputCreateRequest,
+    },
+    RemoveOutput {
+        output: String,
+    },
+    SetMute {
+        input: String,
+        mute: bool,
+    },
+    SetVolume {
+        input: String,
+        volume: f64,
+    },
+    /// Moves and resizes an input, keeping its current stacking order unless `zorder` is given.
+    SetPosition {
+        input: String,
+        xpos: i32,
+        ypos: i32,
+        width: i32,
+        height: i32,
+        #[serde(default)]
+        zorder: Option<u32>,
+    },
+    SetAlpha {
+        input: String,
+        alpha: f64,
+    },
+    /// Starts ISO recording of `inputs`, or of every input when omitted.
+    StartIso {
+        #[serde(default)]
+        config: IsoConfig,
+        #[serde(default)]
+        inputs: Option<Vec<String>>,
+    },
+    StopIso,
+    /// Starts, stops or splits the recording of a `File` output.
+    Recording {
+        output: String,
+        recording: RecordingAction,
+        #[serde(default)]
+        location: Option<String>,
+    },
+}
+
+impl Action {
+    /// Runs the action against a mixer. Crossfades are not started here, see
+    /// [`crossfade`](#method.crossfade).
+    pub fn execute(self, mixers: &mut super::Mixers, mixer_name: &str) -> Result<(), Error> {
+        let mixer = mixers.mixers.get_mut(mixer_name).ok_or(Error::NotFound)?;
+
+        match self {
+            Action::SetActive {
+                input,
+                crossfade_ms,
+            } => {
+                mixer.input_set_active(&input)?;
+                if crossfade_ms > 0 {
+                    if let Some(input) = mixer.inputs.get_mut(&input) {
+                        input.set_alpha(0.0, false)?;
+                    }
+                }
+            }
+            Action::AddInput { input } => mixer.input_add(input.create()?)?,
+            Action::RemoveInput { input } => mixer.input_remove(&input)?,
+            Action::AddOutput { output } => {
+                let video = mixer.config().video;
+                mixer.output_add(output.create(&video)?)?
+            }
+            Action::RemoveOutput { output } => super::output::remove_output(mixer, &output)?,
+            Action::SetMute { input, mute } => {
+                let input = mixer.inputs.get_mut(&input).ok_or(Error::NotFound)?;
+                input.set_mute(mute, true)?;
+            }
+            Action::SetVolume { input, volume } => {
+                let input = mixer.inputs.get_mut(&input).ok_or(Error::NotFound)?;
+                input.set_volume(volume, true)?;
+            }
+            Action::SetPosition {
+                input,
+                xpos,
+                ypos,
+                width,
+                height,
+                zorder,
+            } => {
+                let input = mixer.inputs.get_mut(&input).ok_or(Error::NotFound)?;
+                input.set_xpos(xpos, true)?;
+                input.set_ypos(ypos, true)?;
+                input.set_width(width, true)?;
+                input.set_height(height, true)?;
+                if let Some(zorder) = zorder {
+                    input.set_zorder(zorder, true)?;
+                }
+            }
+            Action::SetAlpha { input, alpha } => {
+                let input = mixer.inputs.get_mut(&input).ok_or(Error::NotFound)?;
+                input.set_alpha(alpha, true)?;
+            }
+            Action::StartIso { config, inputs } => mixer.iso_start(config, inputs)?,
+            Action::StopIso => {
+                mixer.iso_stop()?;
+            }
+            Action::Recording {
+                output,
+                recording,
+                location,
+            } => {
+                super::output::control_recording(mixer, &output, recording, location.as_deref())?;
+            }
+        }
+
+        // Layout changes may have put an input in or out of view.
+        mixer.tally_update();
+        Ok(())
+    }
+
+    /// Returns the role needed to save the action in a rundown or macro. As the outputs it removes
+    /// may not exist yet, removing any output needs an admin. Recording, including adding an input
+    /// which is recorded, writes files on the server so it needs an admin like the routes doing so.
+    pub fn required_role(&self) -> Role {
+        match self {
+            Action::AddInput { input } if input.record => Role::Admin,
+            Action::AddOutput { output } if output.admin_only() => Role::Admin,
+            Action::RemoveOutput { .. } => Role::Admin,
+            Action::StartIso { .. } | Action::StopIso | Action::Recording { .. } => Role::Admin,
+            _ => Role::Operator,
+        }
+    }
+
+    /// Returns whether a location of the input or output the action adds was sent back redacted.
+    /// Saving it would replace the secret with the redaction marker.
+    pub fn redacted(&self) -> bool {
+        match self {
+            Action::AddInput { input } => {
+                input.location.is_redacted() || input.items.iter().any(SecretUrl::is_redacted)
+            }
+            Action::AddOutput { output } => output.location.is_redacted(),
+            _ => false,
+        }
+    }
+
+    /// Returns the input and duration of the crossfade to start once the action has run.
+    pub fn crossfade(&self) -> Option<(String, u64)> {
+        match self {
+            Action::SetActive {
+                input,
+                crossfade_ms,
+            } if *crossfade_ms > 0 => Some((input.clone(), *crossfade_ms)),
+            _ => None,
+        }
+    }
+
+    /// Checks the inputs and outputs referenced by the action exist, updating them with the ones
+    /// it adds or removes. Returns a description of the problem otherwise.
+    pub fn check(
+        &self,
+        inputs: &mut HashSet<String>,
+        outputs: &mut HashSet<String>,
+    ) -> Option<String> {
+        match self {
+            Action::AddInput { input } if !inputs.insert(input.name.clone()) => {
+                Some(format!("input '{}' already exists", input.name))
+            }
+            Action::RemoveInput { input } if !inputs.remove(input) => {
+                Some(format!("input '{}' does not exist", input))
+            }
+            Action::AddOutput { output } if !outputs.insert(output.name.clone()) => {
+                Some(format!("output '{}' already exists", output.name))
+            }
+            Action::RemoveOutput { output } if !outputs.remove(output) => {
+                Some(format!("output '{}' does not exist", output))
+            }
+            Action::Recording { output, .. } if !outputs.contains(output) => {
+                Some(format!("output '{}' does not exist", output))
+            }
+            Action::StartIso {
+                inputs: Some(names),
+                ..
+            } => names
+                .iter()
+                .find(|name| !inputs.contains(*name))
+                .map(|name| format!("input '{}' does not exist", name)),
+            Action::SetActive { input, .. }
+            | Action::SetMute { input, .. }
+            | Action::SetVolume { input, .. }
+            | Action::SetPosition { input, .. }
+            | Action::SetAlpha { input, .. }
+                if !inputs.contains(input) =>
+            {
+                Some(format!("input '{}' does not exist", input))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Fades an input from transparent to its configured alpha over the top of the previously
+/// active input.
+pub async fn crossfade(
+    mixers: Arc<Mutex<super::Mixers>>,
+    mixer_name: String,
+    input_name: String,
+    duration_ms: u64,
+) {
+    let steps = (duration_ms / CROSSFADE_STEP_MS).max(1);
+
+    for step in 1..=steps {
+        tokio::time::delay_for(Duration::from_millis(CROSSFADE_STEP_MS)).await;
+
+        let mut mixers = mixers.lock().await;
+        let mixer = match mixers.mixers.get_mut(&mixer_name) {
+            Some(mixer) => mixer,
+            None => return,
+        };
+        let input = match mixer.inputs.get_mut(&input_name) {
+            Some(input) => input,
+            None => return,
+        };
+
+        let alpha = input.config().video.alpha * step as f64 / steps as f64;
+        if let Err(e) = input.set_alpha(alpha, false) {
+            tracing::error!("failed to crossfade input '{}': {}", input_name, e);
+            return;
+        }
+
+        // The inputs below stay visible until the fade completes
+        if step == steps {
+            mixer.tally_update();
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use super::{error, okay, Error, JsonResult};
+use crate::{mixer::AudioBus, AudioMode};
+
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::Mutex;
+use warp::Filter;
+
+/// HTTP Request for updating the [`AudioBus`](../mixer/struct.AudioBus.html) of a
+/// [`mixer`](../mixer/struct.Mixer.html).
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct UpdateRequest {
+    /// Pairs the video of an input (key) with the audio of another input (value).
+    #[serde(default)]
+    pub pairings: HashMap<String, String>,
+}
+
+impl UpdateRequest {
+    /// Constructs a new `UpdateRequest` from a json body.
+    /// This function consumes the http request body through warp::body::json().
+    pub fn from_json_body() -> impl Filter<Extract = (Self,), Error = warp::Rejection> + Clone {
+        // When accepting a body, we want a JSON body
+        // (and to reject huge payloads)...
+        warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+    }
+}
+
+/// HTTP Response for the audio of a [`mixer`](../mixer/struct.Mixer.html)
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Audio {
+    pub active_input: Option<String>,
+    pub program_input: Option<String>,
+    pub pairings: HashMap<String, String>,
+    pub inputs: Vec<InputAudio>,
+}
+
+/// HTTP Response for the audio state of a single [`input::Input`](../input/struct.Input.html)
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct InputAudio {
+    pub name: String,
+    pub mode: AudioMode,
+    pub volume: f64,
+    pub audible: bool,
+}
+
+/// HTTP Handler for retrieving the audio bus of a given mixer along with the audio state of
+/// each of its inputs.
+#[tracing::instrument(skip(mixers))]
+pub async fn get(mixer_name: String, mixers: Arc<Mutex<super::Mixers>>) -> JsonResult {
+    let mixers = mixers.lock().await;
+    let mixer = match mixers.mixers.get(&mixer_name) {
+        None => return error(Error::NotFound),
+        Some(mixer) => mixer,
+    };
+
+    let inputs: Vec<InputAudio> = mixer
+        .inputs
+        .iter()
+        .map(|(name, input)| {
+            let config = input.config();
+            InputAudio {
+                name: name.clone(),
+                mode: config.audio.mode,
+                volume: config.audio.volume,
+                audible: mixer.input_audible(name),
+            }
+        })
+        .collect();
+
+    okay(&Audio {
+        active_input: mixer.active_input(),
+        program_input: mixer.program_audio_input(),
+        pairings: mixer.audio_bus().pairings,
+        inputs,
+    })
+}
+
+/// HTTP Handler for replacing the audio bus of a given mixer. Every input referenced by a pairing
+/// must exist.
+#[tracing::instrument(skip(mixers))]
+pub async fn update(
+    mixer_name: String,
+    request: UpdateRequest,
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> JsonResult {
+    let mut mixers = mixers.lock().await;
+    let mixer = match mixers.mixers.get_mut(&mixer_name) {
+        None => return error(Error::NotFound),
+        Some(mixer) => mixer,
+    };
+
+    let audio_bus = AudioBus {
+        pairings: request.pairings,
+    };
+
+    match mixer.set_audio_bus(audio_bus) {
+        Ok(_) => okay(mixer.audio_bus()),
+        Err(e) => error(Error::Mixer(e)),
+    }
+}
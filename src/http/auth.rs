@@ -0,0 +1,185 @@
+use super::Error;
+use serde::{Deserialize, Serialize};
+use std::{str::FromStr, sync::Arc};
+use warp::{http::Method, path::FullPath, reject, Filter, Rejection};
+
+/// What a client is allowed to do with the API. Every role may do everything the roles before it
+/// may do.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    /// Read only access.
+    Viewer,
+    /// Switching inputs, changing the layout and managing inputs, rundowns and macros.
+    Operator,
+    /// Creating mixers, outputs sending to external destinations and recordings.
+    Admin,
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(role: &str) -> Result<Self, Self::Err> {
+        match role.to_lowercase().as_str() {
+            "viewer" => Ok(Role::Viewer),
+            "operator" => Ok(Role::Operator),
+            "admin" => Ok(Role::Admin),
+            _ => Err(format!("unknown role `{}`", role)),
+        }
+    }
+}
+
+/// Token sent as `Authorization: Bearer <token>`.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Token {
+    pub token: String,
+    pub role: Role,
+}
+
+impl FromStr for Token {
+    type Err = String;
+
+    /// Parses a token from `role:token`.
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw.splitn(2, ':').collect::<Vec<_>>().as_slice() {
+            [role, token] if !token.is_empty() => Ok(Token {
+                token: token.to_string(),
+                role: role.parse()?,
+            }),
+            _ => Err("expected `role:token`".to_string()),
+        }
+    }
+}
+
+/// User logging in with basic auth.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct User {
+    pub username: String,
+    pub password: String,
+    pub role: Role,
+}
+
+impl FromStr for User {
+    type Err = String;
+
+    /// Parses a user from `role:username:password`.
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw.splitn(3, ':').collect::<Vec<_>>().as_slice() {
+            [role, username, password] if !username.is_empty() && !password.is_empty() => {
+                Ok(User {
+                    username: username.to_string(),
+                    password: password.to_string(),
+                    role: role.parse()?,
+                })
+            }
+            _ => Err("expected `role:username:password`".to_string()),
+        }
+    }
+}
+
+/// Credentials accepted by the API. Without any, every request is allowed as an admin.
+#[derive(Default, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct Config {
+    pub tokens: Vec<Token>,
+    pub users: Vec<User>,
+}
+
+impl Config {
+    pub fn enabled(&self) -> bool {
+        !self.tokens.is_empty() || !self.users.is_empty()
+    }
+
+    /// Returns the role granted by the value of an `Authorization` header, or `None` when it
+    /// doesn't match any credentials.
+    pub fn role(&self, authorization: Option<&str>) -> Option<Role> {
+        if !self.enabled() {
+            return Some(Role::Admin);
+        }
+
+        let authorization = authorization?.trim();
+        let (scheme, credentials) = authorization.split_at(authorization.find(' ')?);
+        let credentials = credentials.trim();
+        match scheme.to_lowercase().as_str() {
+            "bearer" => self
+                .tokens
+                .iter()
+                .find(|token| secure_eq(&token.token, credentials))
+                .map(|token| token.role),
+            "basic" => {
+                let decoded = String::from_utf8(base64::decode(credentials).ok()?).ok()?;
+                let (username, password) = decoded.split_at(decoded.find(':')?);
+                let password = &password[1..];
+                self.users
+                    .iter()
+                    .find(|user| {
+                        secure_eq(&user.username, username) & secure_eq(&user.password, password)
+                    })
+                    .map(|user| user.role)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Returns the role a request needs. Reading is open to viewers, creating and deleting mixers,
+/// recording, revealing secret locations and dumping pipelines, which include every location in
+/// clear, is left to admins and everything else needs an operator.
+/// Handlers which can reach external destinations or write files check for admins themselves.
+pub fn required_role(method: &Method, path: &str) -> Role {
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    match (method, segments.as_slice()) {
+        (_, ["mixers", _, "inputs", _, "reveal"])
+        | (_, ["mixers", _, "outputs", _, "reveal"])
+        | (_, ["mixers", _, "debug"]) => Role::Admin,
+        (&Method::GET, _) | (&Method::HEAD, _) => Role::Viewer,
+        // Recordings are written wherever the request says
+        (_, ["mixers", _, "iso", _]) | (_, ["mixers", _, "outputs", _, "recording"]) => Role::Admin,
+        (&Method::POST, ["mixers"]) | (&Method::DELETE, ["mixers", _]) => Role::Admin,
+        _ => Role::Operator,
+    }
+}
+
+/// Rejects requests without credentials granting the role their route requires.
+pub(crate) fn authorize(
+    config: Arc<Config>,
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::method()
+        .and(warp::path::full())
+        .and(warp::header::optional::<String>("authorization"))
+        .and_then(
+            move |method: Method, path: FullPath, authorization: Option<String>| {
+                let config = config.clone();
+                async move {
+                    let role = config
+                        .role(authorization.as_deref())
+                        .ok_or_else(|| reject::custom(Error::Unauthorized))?;
+                    if role < required_role(&method, path.as_str()) {
+                        return Err(reject::custom(Error::Forbidden));
+                    }
+                    Ok(())
+                }
+            },
+        )
+        .untuple_one()
+}
+
+/// Extracts the role granted to a request, for handlers needing more than their route requires.
+/// Only use behind [`authorize`](fn.authorize.html).
+pub(crate) fn role(
+    config: Arc<Config>,
+) -> impl Filter<Extract = (Role,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("authorization").map(move |authorization: Option<String>| {
+        config
+            .role(authorization.as_deref())
+            .unwrap_or(Role::Viewer)
+    })
+}
+
+/// Compares secrets in time independent of where they differ.
+fn secure_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
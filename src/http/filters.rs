@@ -1,4 +1,5 @@
-use super::{input, mixer, output, recover};
+use super::{audio, auth, input, macros, mixer, output, recover, replay, rundown};
+use crate::snapshot::ImageFormat;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use warp::*;
@@ -10,24 +11,96 @@ fn with_mixers(
     warp::any().map(move || mixers.clone())
 }
 
-/// Generates HTTP routes.
+/// Generates HTTP routes, allowing only requests whose credentials grant the role a route needs.
 pub fn routes(
     mixers: Arc<Mutex<super::Mixers>>,
+    auth: Arc<auth::Config>,
 ) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
-    mixer_list(mixers.clone())
+    let routes = mixer_list(mixers.clone())
         .or(mixer_get(mixers.clone()))
         .or(mixer_create(mixers.clone()))
         .or(mixer_debug(mixers.clone()))
+        .or(mixer_loudness(mixers.clone()))
+        .or(mixer_events(mixers.clone()))
+        .or(mixer_snapshot(mixers.clone()))
+        .or(input_snapshot(mixers.clone()))
+        .or(mixer_tally(mixers.clone()))
+        .or(input_tally(mixers.clone()))
+        .or(input_tally_page(mixers.clone()))
+        .or(mixer_iso_get(mixers.clone()))
+        .or(mixer_iso_start(mixers.clone()))
+        .or(mixer_iso_stop(mixers.clone()))
         .or(input_list(mixers.clone()))
         .or(input_get(mixers.clone()))
-        .or(input_add(mixers.clone()))
+        .or(input_add(mixers.clone(), auth.clone()))
         .or(input_update(mixers.clone()))
+        .or(input_reveal(mixers.clone()))
         .or(input_remove(mixers.clone()))
         .or(input_set_active(mixers.clone()))
+        .or(input_reset_color(mixers.clone()))
+        .or(input_playback_get(mixers.clone()))
+        .or(input_playback(mixers.clone()))
+        .or(input_playlist(mixers.clone()))
+        .or(input_playlist_next(mixers.clone()))
+        .or(audio_get(mixers.clone()))
+        .or(audio_update(mixers.clone()))
         .or(output_list(mixers.clone()))
         .or(output_get(mixers.clone()))
-        .or(output_add(mixers.clone()))
-        .or(output_remove(mixers))
+        .or(output_add(mixers.clone(), auth.clone()))
+        .or(output_reveal(mixers.clone()))
+        .or(output_remove(mixers.clone(), auth.clone()))
+        .or(output_recording(mixers.clone()))
+        .or(rundown_get(mixers.clone()))
+        .or(rundown_update(mixers.clone(), auth.clone()))
+        .or(rundown_hold(mixers.clone()))
+        .or(rundown_dry_run(mixers.clone()))
+        .or(macro_list(mixers.clone()))
+        .or(macro_update(mixers.clone(), auth.clone()))
+        .or(macro_remove(mixers.clone()))
+        .or(macro_run(mixers.clone()))
+        .or(replay_list(mixers.clone()))
+        .or(replay_play(mixers));
+
+    auth::authorize(auth).and(routes).recover(recover)
+}
+
+/// Redirects every request to the same path and query over HTTPS on `port`, for a plain HTTP
+/// listener next to the API.
+pub(crate) fn https_redirect(
+    port: u16,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::header::<String>("host")
+        .and(warp::path::full())
+        .and(warp::query::raw().or(warp::any().map(String::new)).unify())
+        .map(move |host: String, path: path::FullPath, query: String| {
+            let location = format!(
+                "https://{}{}{}{}{}",
+                strip_port(&host),
+                if port == 443 {
+                    "".to_string()
+                } else {
+                    format!(":{}", port)
+                },
+                path.as_str(),
+                if query.is_empty() { "" } else { "?" },
+                query
+            );
+            match location.parse::<http::Uri>() {
+                Ok(uri) => redirect::temporary(uri).into_response(),
+                Err(_) => reply::with_status("invalid host", http::StatusCode::BAD_REQUEST)
+                    .into_response(),
+            }
+        })
+}
+
+/// Returns a `Host` header without its port, keeping the brackets of IPv6 addresses.
+fn strip_port(host: &str) -> &str {
+    match host.rfind(':') {
+        Some(index) if !host.ends_with(']') && host[index + 1..].parse::<u16>().is_ok() => {
+            &host[..index]
+        }
+        _ => host,
+    }
 }
 
 /// Setup route for `POST /mixers`
@@ -75,13 +148,141 @@ pub(crate) fn mixer_debug(
         .recover(recover)
 }
 
+/// Setup route for `GET /mixers/name/events`
+pub(crate) fn mixer_events(
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / "events")
+        .and(warp::get())
+        .and(with_mixers(mixers))
+        .and_then(mixer::events)
+        .recover(recover)
+}
+
+/// Extracts the format of a `snapshot.jpg` or `snapshot.png` path segment.
+fn snapshot_format() -> impl Filter<Extract = (ImageFormat,), Error = warp::Rejection> + Clone {
+    warp::path("snapshot.jpg")
+        .map(|| ImageFormat::JPEG)
+        .or(warp::path("snapshot.png").map(|| ImageFormat::PNG))
+        .unify()
+}
+
+/// Setup route for `GET /mixers/name/snapshot.jpg` and `GET /mixers/name/snapshot.png`
+pub(crate) fn mixer_snapshot(
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / ..)
+        .and(snapshot_format())
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(warp::query::<mixer::SnapshotQuery>())
+        .and(with_mixers(mixers))
+        .and_then(mixer::snapshot)
+        .recover(recover)
+}
+
+/// Setup route for `GET /mixers/name/inputs/name/snapshot.jpg` and
+/// `GET /mixers/name/inputs/name/snapshot.png`
+pub(crate) fn input_snapshot(
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / "inputs" / String / ..)
+        .and(snapshot_format())
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(warp::query::<mixer::SnapshotQuery>())
+        .and(with_mixers(mixers))
+        .and_then(input::snapshot)
+        .recover(recover)
+}
+
+/// Setup route for `GET /mixers/name/tally`
+pub(crate) fn mixer_tally(
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / "tally")
+        .and(warp::get())
+        .and(with_mixers(mixers))
+        .and_then(mixer::tally)
+        .recover(recover)
+}
+
+/// Setup route for `GET /mixers/name/inputs/name/tally`
+pub(crate) fn input_tally(
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / "inputs" / String / "tally")
+        .and(warp::get())
+        .and(with_mixers(mixers))
+        .and_then(input::tally)
+        .recover(recover)
+}
+
+/// Setup route for `GET /mixers/name/inputs/name/tally.html`
+pub(crate) fn input_tally_page(
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / "inputs" / String / "tally.html")
+        .and(warp::get())
+        .and(with_mixers(mixers))
+        .and_then(input::tally_page)
+        .recover(recover)
+}
+
+/// Setup route for `GET /mixers/name/iso`
+pub(crate) fn mixer_iso_get(
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / "iso")
+        .and(warp::get())
+        .and(with_mixers(mixers))
+        .and_then(mixer::iso_get)
+        .recover(recover)
+}
+
+/// Setup route for `POST /mixers/name/iso/start`
+pub(crate) fn mixer_iso_start(
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / "iso" / "start")
+        .and(warp::post())
+        .and(mixer::IsoStartRequest::from_json_body())
+        .and(with_mixers(mixers))
+        .and_then(mixer::iso_start)
+        .recover(recover)
+}
+
+/// Setup route for `POST /mixers/name/iso/stop`
+pub(crate) fn mixer_iso_stop(
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / "iso" / "stop")
+        .and(warp::post())
+        .and(with_mixers(mixers))
+        .and_then(mixer::iso_stop)
+        .recover(recover)
+}
+
+/// Setup route for `GET /mixer/name/loudness`
+pub(crate) fn mixer_loudness(
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / "loudness")
+        .and(warp::get())
+        .and(with_mixers(mixers))
+        .and_then(mixer::loudness)
+        .recover(recover)
+}
+
 /// Setup route for `POST /mixers/name/inputs`
 pub(crate) fn input_add(
     mixers: Arc<Mutex<super::Mixers>>,
+    auth: Arc<auth::Config>,
 ) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
     warp::path!("mixers" / String / "inputs")
         .and(warp::post())
         .and(input::CreateRequest::from_json_body())
+        .and(auth::role(auth))
         .and(with_mixers(mixers))
         .and_then(input::add)
         .recover(recover)
@@ -109,6 +310,17 @@ pub(crate) fn input_get(
         .recover(recover)
 }
 
+/// Setup route for `GET /mixers/name/inputs/name/reveal`
+pub(crate) fn input_reveal(
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / "inputs" / String / "reveal")
+        .and(warp::get())
+        .and(with_mixers(mixers))
+        .and_then(input::reveal)
+        .recover(recover)
+}
+
 /// Setup route for `PUT /mixers/name/inputs/name`
 pub(crate) fn input_update(
     mixers: Arc<Mutex<super::Mixers>>,
@@ -143,6 +355,85 @@ pub(crate) fn input_set_active(
         .recover(recover)
 }
 
+/// Setup route for `POST /mixers/name/inputs/name/reset_color`
+pub(crate) fn input_reset_color(
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / "inputs" / String / "reset_color")
+        .and(warp::post())
+        .and(with_mixers(mixers))
+        .and_then(input::reset_color)
+        .recover(recover)
+}
+
+/// Setup route for `GET /mixers/name/inputs/name/playback`
+pub(crate) fn input_playback_get(
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / "inputs" / String / "playback")
+        .and(warp::get())
+        .and(with_mixers(mixers))
+        .and_then(input::playback_get)
+        .recover(recover)
+}
+
+/// Setup route for `POST /mixers/name/inputs/name/playback`
+pub(crate) fn input_playback(
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / "inputs" / String / "playback")
+        .and(warp::post())
+        .and(input::PlaybackRequest::from_json_body())
+        .and(with_mixers(mixers))
+        .and_then(input::playback)
+        .recover(recover)
+}
+
+/// Setup route for `GET /mixers/name/inputs/name/playlist`
+pub(crate) fn input_playlist(
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / "inputs" / String / "playlist")
+        .and(warp::get())
+        .and(with_mixers(mixers))
+        .and_then(input::playlist)
+        .recover(recover)
+}
+
+/// Setup route for `POST /mixers/name/inputs/name/playlist/next`
+pub(crate) fn input_playlist_next(
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / "inputs" / String / "playlist" / "next")
+        .and(warp::post())
+        .and(with_mixers(mixers))
+        .and_then(input::playlist_next)
+        .recover(recover)
+}
+
+/// Setup route for `GET /mixers/name/audio`
+pub(crate) fn audio_get(
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / "audio")
+        .and(warp::get())
+        .and(with_mixers(mixers))
+        .and_then(audio::get)
+        .recover(recover)
+}
+
+/// Setup route for `PUT /mixers/name/audio`
+pub(crate) fn audio_update(
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / "audio")
+        .and(warp::put())
+        .and(audio::UpdateRequest::from_json_body())
+        .and(with_mixers(mixers))
+        .and_then(audio::update)
+        .recover(recover)
+}
+
 /// Setup route for `GET /mixers/name/outputs`
 pub(crate) fn output_list(
     mixers: Arc<Mutex<super::Mixers>>,
@@ -157,10 +448,12 @@ pub(crate) fn output_list(
 /// Setup route for `POST /mixers/name/outputs`
 pub(crate) fn output_add(
     mixers: Arc<Mutex<super::Mixers>>,
+    auth: Arc<auth::Config>,
 ) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
     warp::path!("mixers" / String / "outputs")
         .and(warp::post())
         .and(output::CreateRequest::from_json_body())
+        .and(auth::role(auth))
         .and(with_mixers(mixers))
         .and_then(output::add)
         .recover(recover)
@@ -177,13 +470,157 @@ pub(crate) fn output_get(
         .recover(recover)
 }
 
+/// Setup route for `GET /mixers/name/outputs/name/reveal`
+pub(crate) fn output_reveal(
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / "outputs" / String / "reveal")
+        .and(warp::get())
+        .and(with_mixers(mixers))
+        .and_then(output::reveal)
+        .recover(recover)
+}
+
 /// Setup route for `DELETE /mixers/name/outputs/name`
 pub(crate) fn output_remove(
     mixers: Arc<Mutex<super::Mixers>>,
+    auth: Arc<auth::Config>,
 ) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
     warp::path!("mixers" / String / "outputs" / String)
         .and(warp::delete())
+        .and(auth::role(auth))
         .and(with_mixers(mixers))
         .and_then(output::remove)
         .recover(recover)
 }
+
+/// Setup route for `POST /mixers/name/outputs/name/recording`
+pub(crate) fn output_recording(
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / "outputs" / String / "recording")
+        .and(warp::post())
+        .and(output::RecordingRequest::from_json_body())
+        .and(with_mixers(mixers))
+        .and_then(output::recording)
+        .recover(recover)
+}
+
+/// Setup route for `GET /mixers/name/rundown`
+pub(crate) fn rundown_get(
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / "rundown")
+        .and(warp::get())
+        .and(with_mixers(mixers))
+        .and_then(rundown::get)
+        .recover(recover)
+}
+
+/// Setup route for `PUT /mixers/name/rundown`
+pub(crate) fn rundown_update(
+    mixers: Arc<Mutex<super::Mixers>>,
+    auth: Arc<auth::Config>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / "rundown")
+        .and(warp::put())
+        .and(rundown::UpdateRequest::from_json_body())
+        .and(auth::role(auth))
+        .and(with_mixers(mixers))
+        .and_then(rundown::update)
+        .recover(recover)
+}
+
+/// Setup route for `POST /mixers/name/rundown/hold`
+pub(crate) fn rundown_hold(
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / "rundown" / "hold")
+        .and(warp::post())
+        .and(rundown::HoldRequest::from_json_body())
+        .and(with_mixers(mixers))
+        .and_then(rundown::hold)
+        .recover(recover)
+}
+
+/// Setup route for `POST /mixers/name/rundown/dry_run`
+pub(crate) fn rundown_dry_run(
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / "rundown" / "dry_run")
+        .and(warp::post())
+        .and(rundown::UpdateRequest::from_json_body())
+        .and(with_mixers(mixers))
+        .and_then(rundown::dry_run_cues)
+        .recover(recover)
+}
+
+/// Setup route for `GET /mixers/name/macros`
+pub(crate) fn macro_list(
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / "macros")
+        .and(warp::get())
+        .and(with_mixers(mixers))
+        .and_then(macros::list)
+        .recover(recover)
+}
+
+/// Setup route for `PUT /mixers/name/macros/name`
+pub(crate) fn macro_update(
+    mixers: Arc<Mutex<super::Mixers>>,
+    auth: Arc<auth::Config>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / "macros" / String)
+        .and(warp::put())
+        .and(macros::Macro::from_json_body())
+        .and(auth::role(auth))
+        .and(with_mixers(mixers))
+        .and_then(macros::update)
+        .recover(recover)
+}
+
+/// Setup route for `DELETE /mixers/name/macros/name`
+pub(crate) fn macro_remove(
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / "macros" / String)
+        .and(warp::delete())
+        .and(with_mixers(mixers))
+        .and_then(macros::remove)
+        .recover(recover)
+}
+
+/// Setup route for `POST /mixers/name/macros/name/run`
+pub(crate) fn macro_run(
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / "macros" / String / "run")
+        .and(warp::post())
+        .and(with_mixers(mixers))
+        .and_then(macros::run)
+        .recover(recover)
+}
+
+/// Setup route for `GET /mixers/name/replay`
+pub(crate) fn replay_list(
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / "replay")
+        .and(warp::get())
+        .and(with_mixers(mixers))
+        .and_then(replay::list)
+        .recover(recover)
+}
+
+/// Setup route for `POST /mixers/name/replay`
+pub(crate) fn replay_play(
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path!("mixers" / String / "replay")
+        .and(warp::post())
+        .and(replay::PlayRequest::from_json_body())
+        .and(with_mixers(mixers))
+        .and_then(replay::play)
+        .recover(recover)
+}
@@ -1,11 +1,18 @@
-use super::{error, message_response, okay, Error, JsonResult};
+use super::{
+    auth::Role, error, message_response, mixer::SnapshotQuery, okay, output::RevealedLocation,
+    snapshot_response, Error, JsonResult,
+};
 use crate::input::{Config as InputConfig, Input as MixerInput};
-use crate::{AudioConfig, VideoConfig};
+use crate::mixer::{Error as MixerError, Tally};
+use crate::secret::SecretUrl;
+use crate::{
+    snapshot::ImageFormat, AudioConfig, ColorConfig, PlaybackConfig, ReplayConfig, VideoConfig,
+};
 
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use warp::{http::StatusCode, Filter};
+use warp::{http::StatusCode, reject, reply, Filter, Rejection, Reply};
 
 /// HTTP Request for creating a new [`input::Input`](../input/struct.Input.html)
 /// to be used by the [`mixer`](../mixer/struct.Mixer.html).
@@ -13,13 +20,27 @@ use warp::{http::StatusCode, Filter};
 pub struct CreateRequest {
     pub name: String,
     pub input_type: String,
-    pub location: String,
+    pub location: SecretUrl,
     #[serde(default)]
     pub audio: AudioConfig,
     #[serde(default)]
     pub video: VideoConfig,
     #[serde(default)]
     pub record: bool,
+    #[serde(default)]
+    pub playback: PlaybackConfig,
+    /// Restarts `Audio` and `Playlist` inputs from the beginning when they reach the end.
+    #[serde(default)]
+    pub looping: bool,
+    /// URIs played in order by `Playlist` inputs.
+    #[serde(default)]
+    pub items: Vec<SecretUrl>,
+    /// Shuffles the items of `Playlist` inputs, again on every loop.
+    #[serde(default)]
+    pub shuffle: bool,
+    /// Rolling buffer of `Replay` inputs, whose location is the input to buffer or `program`.
+    #[serde(default)]
+    pub replay: ReplayConfig,
 }
 
 impl CreateRequest {
@@ -30,6 +51,37 @@ impl CreateRequest {
         // (and to reject huge payloads)...
         warp::body::content_length_limit(1024 * 16).and(warp::body::json())
     }
+
+    /// Creates the [`input::Input`](../input/struct.Input.html) described by this request.
+    pub fn create(self) -> Result<MixerInput, Error> {
+        let config = InputConfig {
+            name: self.name.clone(),
+            video: self.video,
+            audio: self.audio,
+            record: self.record,
+            playback: self.playback,
+        };
+
+        match self.input_type.as_str() {
+            "URI" => MixerInput::create_uri(config, self.location.reveal()).map_err(Error::Mixer),
+            "Fake" => MixerInput::create_fake(config).map_err(Error::Mixer),
+            "Test" => MixerInput::create_test(config).map_err(Error::Mixer),
+            "Audio" => MixerInput::create_audio(config, self.location.reveal(), self.looping)
+                .map_err(Error::Mixer),
+            "Playlist" => {
+                let items = self
+                    .items
+                    .iter()
+                    .map(|item| item.reveal().to_string())
+                    .collect();
+                MixerInput::create_playlist(config, items, self.shuffle, self.looping)
+                    .map_err(Error::Mixer)
+            }
+            "Replay" => MixerInput::create_replay(config, self.location.reveal(), self.replay)
+                .map_err(Error::Mixer),
+            _ => Err(Error::Unknown),
+        }
+    }
 }
 
 /// HTTP Request for update a [`input::Input`](../input/struct.Input.html)
@@ -50,39 +102,62 @@ impl UpdateRequest {
     }
 }
 
+/// Playback action applied to a file [`input::Input`](../input/struct.Input.html).
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub enum PlaybackAction {
+    Play,
+    Pause,
+    /// Seeks to `position_ms`.
+    Seek,
+    /// Seeks back to the in point.
+    Restart,
+}
+
+/// HTTP Request for controlling the playback of a file [`input::Input`](../input/struct.Input.html)
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PlaybackRequest {
+    pub action: PlaybackAction,
+    #[serde(default)]
+    pub position_ms: Option<u64>,
+}
+
+impl PlaybackRequest {
+    /// Constructs a new `PlaybackRequest` from a json body.
+    /// This function consumes the http request body through warp::body::json().
+    pub fn from_json_body() -> impl Filter<Extract = (Self,), Error = warp::Rejection> + Clone {
+        // When accepting a body, we want a JSON body
+        // (and to reject huge payloads)...
+        warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+    }
+}
+
 /// HTTP Response for a [`input::Input`](../input/struct.Input.html)
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Input {
     pub name: String,
     pub input_type: String,
-    pub location: String,
+    pub location: SecretUrl,
+    pub av_offset_ms: i64,
 }
 
 /// HTTP Handler for creating an [`input::Input`](../input/struct.Input.html)
 /// It will add the resulting input to the [`mixer`](../mixer/struct.Mixer.html) which will
-/// link the new input to the Gstreamer pipeline.
+/// link the new input to the Gstreamer pipeline. Recording the input writes a file on the server,
+/// which only admins may ask for.
 #[tracing::instrument(skip(mixers))]
 pub async fn add(
     mixer_name: String,
     input: CreateRequest,
+    role: Role,
     mixers: Arc<Mutex<super::Mixers>>,
 ) -> JsonResult {
-    let mut mixers = mixers.lock().await;
-    let config = InputConfig {
-        name: input.name.clone(),
-        video: input.video,
-        audio: input.audio,
-        record: input.record,
-    };
+    if input.record && role < Role::Admin {
+        return error(Error::Forbidden);
+    }
 
-    let input = match input.input_type.as_str() {
-        "URI" => MixerInput::create_uri(config, &input.location).map_err(super::Error::Mixer),
-        "Fake" => MixerInput::create_fake(config).map_err(super::Error::Mixer),
-        "Test" => MixerInput::create_test(config).map_err(super::Error::Mixer),
-        _ => Err(super::Error::Unknown),
-    };
+    let mut mixers = mixers.lock().await;
 
-    let input = match input {
+    let input = match input.create() {
         Err(e) => return error(e),
         Ok(i) => i,
     };
@@ -110,6 +185,7 @@ pub async fn list(mixer_name: String, mixers: Arc<Mutex<super::Mixers>>) -> Json
             name: input.name(),
             input_type: input.input_type(),
             location: input.location(),
+            av_offset_ms: input.config().audio.av_offset_ms,
         })
         .collect();
     okay(&inputs)
@@ -138,11 +214,37 @@ pub async fn get(
         name: input.name(),
         input_type: input.input_type(),
         location: input.location(),
+        av_offset_ms: input.config().audio.av_offset_ms,
     };
 
     okay(&input)
 }
 
+/// HTTP Handler for revealing the location of an [`input::Input`](../input/struct.Input.html)
+/// including its stream key or password. Only admins may use it.
+#[tracing::instrument(skip(mixers))]
+pub async fn reveal(
+    mixer_name: String,
+    input_name: String,
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> JsonResult {
+    let mixers = mixers.lock().await;
+    let input = match mixers
+        .mixers
+        .get(&mixer_name)
+        .and_then(|mixer| mixer.inputs.get(input_name.as_str()))
+    {
+        None => return error(Error::NotFound),
+        Some(input) => input,
+    };
+
+    tracing::info!("Revealing location of input '{}'", input_name);
+    okay(RevealedLocation {
+        name: input.name(),
+        location: input.location().reveal().to_string(),
+    })
+}
+
 /// HTTP Handler for updating an [`input::Input`](../input/struct.Input.html) associated with
 /// a given mixer.
 #[tracing::instrument(skip(mixers))]
@@ -163,39 +265,239 @@ pub async fn update(
         None => return error(Error::NotFound),
     };
 
-    if input.set_volume(request.audio.volume, true).is_err() {
-        return message_response("set_volume failed", StatusCode::INTERNAL_SERVER_ERROR);
+    // Reject an invalid offset before anything else of the input is changed
+    if let Err(e) = crate::input::av_offset_ns(request.audio.av_offset_ms) {
+        return error(Error::Mixer(e));
+    }
+
+    if let Err(e) = input.set_volume(request.audio.volume, true) {
+        return update_failed("set_volume", e);
+    }
+
+    if let Err(e) = input.set_audio_mode(request.audio.mode, true) {
+        return update_failed("set_audio_mode", e);
+    }
+
+    if let Err(e) = input.set_mute(request.audio.mute, true) {
+        return update_failed("set_mute", e);
+    }
+
+    if let Err(e) = input.set_gain(request.audio.gain, true) {
+        return update_failed("set_gain", e);
+    }
+
+    if let Err(e) = input.set_equalizer(request.audio.equalizer, true) {
+        return update_failed("set_equalizer", e);
+    }
+
+    if let Err(e) = input.set_compressor(request.audio.compressor, true) {
+        return update_failed("set_compressor", e);
+    }
+
+    if let Err(e) = input.set_noise_gate(request.audio.noise_gate, true) {
+        return update_failed("set_noise_gate", e);
+    }
+
+    if let Err(e) = input.set_av_offset(request.audio.av_offset_ms, true) {
+        return update_failed("set_av_offset", e);
     }
 
     if let Some(zorder) = request.video.zorder {
-        if input.set_zorder(zorder, true).is_err() {
-            return message_response("set_zorder failed", StatusCode::INTERNAL_SERVER_ERROR);
+        if let Err(e) = input.set_zorder(zorder, true) {
+            return update_failed("set_zorder", e);
         }
     }
 
-    if input.set_width(request.video.width, true).is_err() {
-        return message_response("set_width failed", StatusCode::INTERNAL_SERVER_ERROR);
+    if let Err(e) = input.set_width(request.video.width, true) {
+        return update_failed("set_width", e);
+    }
+
+    if let Err(e) = input.set_height(request.video.height, true) {
+        return update_failed("set_height", e);
+    }
+
+    if let Err(e) = input.set_xpos(request.video.xpos, true) {
+        return update_failed("set_xpos", e);
+    }
+
+    if let Err(e) = input.set_ypos(request.video.ypos, true) {
+        return update_failed("set_ypos", e);
     }
 
-    if input.set_height(request.video.height, true).is_err() {
-        return message_response("set_height failed", StatusCode::INTERNAL_SERVER_ERROR);
+    if let Err(e) = input.set_alpha(request.video.alpha, true) {
+        return update_failed("set_alpha", e);
     }
 
-    if input.set_xpos(request.video.xpos, true).is_err() {
-        return message_response("set_xpos failed", StatusCode::INTERNAL_SERVER_ERROR);
+    if let Err(e) = input.set_chroma_key(request.video.chroma_key, true) {
+        return update_failed("set_chroma_key", e);
     }
 
-    if input.set_ypos(request.video.ypos, true).is_err() {
-        return message_response("set_ypos failed", StatusCode::INTERNAL_SERVER_ERROR);
+    if let Err(e) = input.set_crop(request.video.crop, true) {
+        return update_failed("set_crop", e);
     }
 
-    if input.set_alpha(request.video.alpha, true).is_err() {
-        return message_response("set_alpha failed", StatusCode::INTERNAL_SERVER_ERROR);
+    if let Err(e) = input.set_flip(request.video.flip, true) {
+        return update_failed("set_flip", e);
     }
 
+    if let Err(e) = input.set_rotation(request.video.rotation, true) {
+        return update_failed("set_rotation", e);
+    }
+
+    if let Err(e) = input.set_scaling_mode(request.video.scaling_mode, true) {
+        return update_failed("set_scaling_mode", e);
+    }
+
+    if let Err(e) = input.set_bar_color(request.video.bar_color, true) {
+        return update_failed("set_bar_color", e);
+    }
+
+    if let Err(e) = input.set_color(request.video.color, true) {
+        return update_failed("set_color", e);
+    }
+
+    // The new volume or audio mode may not apply while another input is on the program.
+    if mixer.audio_update().is_err() {
+        return message_response("audio_update failed", StatusCode::INTERNAL_SERVER_ERROR);
+    }
+    mixer.tally_update();
+
     message_response("Input updated", StatusCode::OK)
 }
 
+/// HTTP Handler for resetting the color correction of an [`input::Input`](../input/struct.Input.html)
+/// back to its defaults.
+#[tracing::instrument(skip(mixers))]
+pub async fn reset_color(
+    mixer_name: String,
+    input_name: String,
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> JsonResult {
+    let mut mixers = mixers.lock().await;
+    let mixer = match mixers.mixers.get_mut(&mixer_name) {
+        Some(mixer) => mixer,
+        None => return error(Error::NotFound),
+    };
+
+    let input = match mixer.inputs.get_mut(input_name.as_str()) {
+        Some(input) => input,
+        None => return error(Error::NotFound),
+    };
+
+    match input.set_color(ColorConfig::default(), true) {
+        Ok(_) => message_response("Input color reset", StatusCode::OK),
+        Err(e) => error(Error::Mixer(e)),
+    }
+}
+
+/// HTTP Handler for retrieving the playback position of a file
+/// [`input::Input`](../input/struct.Input.html).
+#[tracing::instrument(skip(mixers))]
+pub async fn playback_get(
+    mixer_name: String,
+    input_name: String,
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> JsonResult {
+    let mixers = mixers.lock().await;
+    let mixer = match mixers.mixers.get(&mixer_name) {
+        None => return error(Error::NotFound),
+        Some(mixer) => mixer,
+    };
+
+    match mixer.inputs.get(input_name.as_str()) {
+        Some(MixerInput::URI(input)) => okay(input.playback()),
+        Some(_) => message_response("Input does not support playback", StatusCode::BAD_REQUEST),
+        None => error(Error::NotFound),
+    }
+}
+
+/// HTTP Handler for playing, pausing, seeking or restarting a file
+/// [`input::Input`](../input/struct.Input.html).
+#[tracing::instrument(skip(mixers))]
+pub async fn playback(
+    mixer_name: String,
+    input_name: String,
+    request: PlaybackRequest,
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> JsonResult {
+    let mut mixers = mixers.lock().await;
+    let mixer = match mixers.mixers.get_mut(&mixer_name) {
+        None => return error(Error::NotFound),
+        Some(mixer) => mixer,
+    };
+
+    let input = match mixer.inputs.get_mut(input_name.as_str()) {
+        Some(MixerInput::URI(input)) => input,
+        Some(_) => {
+            return message_response("Input does not support playback", StatusCode::BAD_REQUEST)
+        }
+        None => return error(Error::NotFound),
+    };
+
+    let result = match (request.action, request.position_ms) {
+        (PlaybackAction::Play, _) => input.play(),
+        (PlaybackAction::Pause, _) => input.pause(),
+        (PlaybackAction::Seek, Some(position_ms)) => input.seek(position_ms),
+        (PlaybackAction::Seek, None) => {
+            return message_response("Seeking requires position_ms", StatusCode::BAD_REQUEST)
+        }
+        (PlaybackAction::Restart, _) => input.restart(),
+    };
+
+    match result {
+        Ok(_) => okay(input.playback()),
+        Err(e) => error(Error::Mixer(e)),
+    }
+}
+
+/// HTTP Handler for retrieving the progress of a playlist
+/// [`input::Input`](../input/struct.Input.html).
+#[tracing::instrument(skip(mixers))]
+pub async fn playlist(
+    mixer_name: String,
+    input_name: String,
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> JsonResult {
+    let mixers = mixers.lock().await;
+    let mixer = match mixers.mixers.get(&mixer_name) {
+        None => return error(Error::NotFound),
+        Some(mixer) => mixer,
+    };
+
+    match mixer.inputs.get(input_name.as_str()) {
+        Some(MixerInput::Playlist(playlist)) => match playlist.status() {
+            Ok(status) => okay(&status),
+            Err(e) => error(Error::Mixer(e)),
+        },
+        Some(_) => message_response("Input is not a playlist", StatusCode::BAD_REQUEST),
+        None => error(Error::NotFound),
+    }
+}
+
+/// HTTP Handler for skipping to the next item of a playlist
+/// [`input::Input`](../input/struct.Input.html).
+#[tracing::instrument(skip(mixers))]
+pub async fn playlist_next(
+    mixer_name: String,
+    input_name: String,
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> JsonResult {
+    let mixers = mixers.lock().await;
+    let mixer = match mixers.mixers.get(&mixer_name) {
+        None => return error(Error::NotFound),
+        Some(mixer) => mixer,
+    };
+
+    match mixer.inputs.get(input_name.as_str()) {
+        Some(MixerInput::Playlist(playlist)) => match playlist.next() {
+            Ok(_) => message_response("Skipped to next item", StatusCode::OK),
+            Err(e) => error(Error::Mixer(e)),
+        },
+        Some(_) => message_response("Input is not a playlist", StatusCode::BAD_REQUEST),
+        None => error(Error::NotFound),
+    }
+}
+
 /// HTTP Handler for removing an [`input::Input`](../input/struct.Input.html) from the associated
 /// mixer.
 #[tracing::instrument(skip(mixers))]
@@ -220,7 +522,8 @@ pub async fn remove(
 /// input.
 ///
 /// This will change the zorder of all other inputs to be lower than this input, it will then
-/// adjust the volume of all other inputs to 0.
+/// mute every input following video which is not the program audio source. Inputs paired with
+/// this one on the mixer's audio bus supply the audio instead.
 ///
 /// Setting an input to active will reset all its configuration to its prior configuration (if it
 /// had been updated prior, due to another input being set active)
@@ -244,3 +547,128 @@ pub async fn set_active(
         Err(e) => error(Error::Mixer(e)),
     }
 }
+
+/// HTTP Handler for retrieving the latest frame of a URI
+/// [`input::Input`](../input/struct.Input.html) as an image.
+#[tracing::instrument(skip(mixers))]
+pub async fn snapshot(
+    mixer_name: String,
+    input_name: String,
+    format: ImageFormat,
+    query: SnapshotQuery,
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> Result<reply::Response, Rejection> {
+    let sample = {
+        let mixers = mixers.lock().await;
+        let mixer = match mixers.mixers.get(&mixer_name) {
+            None => return Err(reject::custom(Error::NotFound)),
+            Some(mixer) => mixer,
+        };
+
+        match mixer.inputs.get(input_name.as_str()) {
+            Some(MixerInput::URI(input)) => input.snapshot(),
+            Some(_) => {
+                return message_response(
+                    "Input does not support snapshots",
+                    StatusCode::BAD_REQUEST,
+                )
+                .map(Reply::into_response)
+            }
+            None => return Err(reject::custom(Error::NotFound)),
+        }
+    };
+
+    match sample {
+        Ok(sample) => snapshot_response(sample, format, query).await,
+        Err(e) => Err(reject::custom(Error::Mixer(e))),
+    }
+}
+
+/// Tally of a single input.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct InputTally {
+    pub input: String,
+    pub tally: Tally,
+}
+
+/// Returns the tally of an input, or `None` when the mixer or input doesn't exist.
+async fn input_tally(
+    mixer_name: &str,
+    input_name: &str,
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> Option<InputTally> {
+    let mixers = mixers.lock().await;
+    let mixer = mixers.mixers.get(mixer_name)?;
+    if !mixer.inputs.contains_key(input_name) {
+        return None;
+    }
+
+    Some(InputTally {
+        input: input_name.to_string(),
+        tally: mixer.tally().get(input_name).copied().unwrap_or(Tally::Off),
+    })
+}
+
+/// HTTP Handler for retrieving the tally of an [`input::Input`](../input/struct.Input.html).
+pub async fn tally(
+    mixer_name: String,
+    input_name: String,
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> JsonResult {
+    match input_tally(&mixer_name, &input_name, mixers).await {
+        Some(tally) => okay(tally),
+        None => message_response("Input not found", StatusCode::NOT_FOUND),
+    }
+}
+
+/// HTTP Handler for a page showing the tally of an [`input::Input`](../input/struct.Input.html)
+/// as a full screen color, for presenters to keep open in a browser. The page reloads itself
+/// every second.
+pub async fn tally_page(
+    mixer_name: String,
+    input_name: String,
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> Result<reply::Response, Rejection> {
+    let tally = match input_tally(&mixer_name, &input_name, mixers).await {
+        Some(tally) => tally,
+        None => return Err(reject::custom(Error::NotFound)),
+    };
+
+    let (color, label) = match tally.tally {
+        Tally::OnAir => ("#d00000", "ON AIR"),
+        Tally::Visible => ("#00a000", "VISIBLE"),
+        Tally::Off => ("#202020", "OFF"),
+    };
+    let page = format!(
+        "<!DOCTYPE html><html><head><meta http-equiv=\"refresh\" content=\"1\">\
+         <title>{input} - {label}</title></head>\
+         <body style=\"margin:0;height:100vh;display:flex;align-items:center;\
+         justify-content:center;background:{color};color:#fff;font:bold 10vw sans-serif\">\
+         {label}</body></html>",
+        input = html_escape(&input_name),
+        label = label,
+        color = color,
+    );
+
+    let response = reply::with_header(reply::html(page), "Cache-Control", "no-store");
+    Ok(response.into_response())
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Responds to an input failing to apply a setting of an update. Settings the input doesn't
+/// support are rejected as invalid.
+fn update_failed(setting: &str, e: MixerError) -> JsonResult {
+    match e {
+        MixerError::InvalidConfig(_) => error(Error::Mixer(e)),
+        _ => message_response(
+            &format!("{} failed", setting),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        ),
+    }
+}
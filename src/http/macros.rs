@@ -0,0 +1,214 @@
+use super::{
+    action::{self, Action},
+    auth::Role,
+    error, message_response, okay, Error, JsonResult,
+};
+use crate::mixer::Error as MixerError;
+
+use serde::{Deserialize, Serialize};
+use std::{sync::Arc, time::Duration};
+use tokio::sync::Mutex;
+use warp::{http::StatusCode, Filter};
+
+/// Step of a [`Macro`](struct.Macro.html).
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Step {
+    #[serde(flatten)]
+    pub action: Action,
+    /// Time to wait before running the step. Consecutive steps without a delay run under a single
+    /// lock of the mixers, so no other request can interleave with them.
+    #[serde(default)]
+    pub delay_ms: u64,
+}
+
+/// Named sequence of [`Action`](../action/enum.Action.html)'s run against a mixer in one call.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct Macro {
+    pub steps: Vec<Step>,
+}
+
+impl Macro {
+    /// Constructs a new `Macro` from a json body.
+    /// This function consumes the http request body through warp::body::json().
+    pub fn from_json_body() -> impl Filter<Extract = (Self,), Error = warp::Rejection> + Clone {
+        // When accepting a body, we want a JSON body
+        // (and to reject huge payloads)...
+        warp::body::content_length_limit(1024 * 64).and(warp::body::json())
+    }
+}
+
+/// Result of running a [`Step`](struct.Step.html).
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub enum StepState {
+    Done,
+    Failed,
+    /// An earlier step failed, so the step was not run.
+    NotRun,
+}
+
+/// HTTP Response for a step of a macro run.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct StepResult {
+    pub step: usize,
+    pub state: StepState,
+    pub error: Option<String>,
+}
+
+/// HTTP Response for a macro run.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Run {
+    pub success: bool,
+    pub steps: Vec<StepResult>,
+}
+
+/// Runs the steps of a macro in order, stopping at the first failure.
+pub async fn run_macro(
+    mixers: &Arc<Mutex<super::Mixers>>,
+    mixer_name: &str,
+    steps: Vec<Step>,
+) -> Vec<StepResult> {
+    let mut results: Vec<StepResult> = Vec::with_capacity(steps.len());
+    let mut guard = Some(mixers.lock().await);
+
+    for (index, step) in steps.into_iter().enumerate() {
+        if results.iter().any(|r| r.state != StepState::Done) {
+            results.push(StepResult {
+                step: index,
+                state: StepState::NotRun,
+                error: None,
+            });
+            continue;
+        }
+
+        if step.delay_ms > 0 {
+            // Let other requests through while waiting.
+            drop(guard.take());
+            tokio::time::delay_for(Duration::from_millis(step.delay_ms)).await;
+        }
+
+        let mut locked = match guard.take() {
+            Some(locked) => locked,
+            None => mixers.lock().await,
+        };
+
+        let crossfade = step.action.crossfade();
+        let result = step.action.execute(&mut locked, mixer_name);
+        guard = Some(locked);
+
+        let result = match result {
+            Ok(()) => {
+                if let Some((input, duration_ms)) = crossfade {
+                    tokio::spawn(action::crossfade(
+                        Arc::clone(mixers),
+                        mixer_name.to_string(),
+                        input,
+                        duration_ms,
+                    ));
+                }
+
+                StepResult {
+                    step: index,
+                    state: StepState::Done,
+                    error: None,
+                }
+            }
+            Err(e) => StepResult {
+                step: index,
+                state: StepState::Failed,
+                error: Some(e.to_string()),
+            },
+        };
+        results.push(result);
+    }
+
+    results
+}
+
+/// HTTP Handler for listing the macros of a mixer.
+#[tracing::instrument(skip(mixers))]
+pub async fn list(mixer_name: String, mixers: Arc<Mutex<super::Mixers>>) -> JsonResult {
+    let mixers = mixers.lock().await;
+    if !mixers.mixers.contains_key(&mixer_name) {
+        return error(Error::NotFound);
+    }
+
+    okay(mixers.macros.get(&mixer_name).cloned().unwrap_or_default())
+}
+
+/// HTTP Handler for creating or replacing a macro of a mixer.
+#[tracing::instrument(skip(mixers))]
+pub async fn update(
+    mixer_name: String,
+    macro_name: String,
+    request: Macro,
+    role: Role,
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> JsonResult {
+    if request
+        .steps
+        .iter()
+        .any(|step| step.action.required_role() > role)
+    {
+        return error(Error::Forbidden);
+    }
+    if request.steps.iter().any(|step| step.action.redacted()) {
+        return error(Error::Mixer(MixerError::InvalidConfig(
+            "locations of steps must not be redacted".to_string(),
+        )));
+    }
+
+    let mut mixers = mixers.lock().await;
+    if !mixers.mixers.contains_key(&mixer_name) {
+        return error(Error::NotFound);
+    }
+
+    mixers
+        .macros
+        .entry(mixer_name)
+        .or_default()
+        .insert(macro_name, request);
+    message_response("Macro saved.", StatusCode::OK)
+}
+
+/// HTTP Handler for removing a macro of a mixer.
+#[tracing::instrument(skip(mixers))]
+pub async fn remove(
+    mixer_name: String,
+    macro_name: String,
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> JsonResult {
+    let mut mixers = mixers.lock().await;
+    match mixers
+        .macros
+        .get_mut(&mixer_name)
+        .and_then(|macros| macros.remove(&macro_name))
+    {
+        Some(_) => message_response("Macro removed.", StatusCode::OK),
+        None => error(Error::NotFound),
+    }
+}
+
+/// HTTP Handler for running a macro of a mixer.
+#[tracing::instrument(skip(mixers))]
+pub async fn run(
+    mixer_name: String,
+    macro_name: String,
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> JsonResult {
+    let steps = match mixers
+        .lock()
+        .await
+        .macros
+        .get(&mixer_name)
+        .and_then(|macros| macros.get(&macro_name))
+    {
+        Some(m) => m.steps.clone(),
+        None => return error(Error::NotFound),
+    };
+
+    let steps = run_macro(&mixers, &mixer_name, steps).await;
+    okay(Run {
+        success: steps.iter().all(|s| s.state == StepState::Done),
+        steps,
+    })
+}
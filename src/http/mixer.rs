@@ -1,5 +1,7 @@
-use super::{error, message_response, okay, JsonResult};
-use crate::{mixer::Config as MixerConfig, AudioConfig, VideoConfig};
+use super::{error, message_response, okay, snapshot_response, Error, JsonResult};
+use crate::{
+    mixer::Config as MixerConfig, snapshot::ImageFormat, IsoConfig, MixerAudioConfig, VideoConfig,
+};
 use serde::{Deserialize, Serialize};
 use std::{
     convert::Infallible,
@@ -8,7 +10,7 @@ use std::{
     sync::Arc,
 };
 use tokio::sync::Mutex;
-use warp::{http::StatusCode, reply, Filter, Reply};
+use warp::{http::StatusCode, reject, reply, Filter, Rejection, Reply};
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct CreateRequest {
@@ -16,7 +18,7 @@ pub struct CreateRequest {
     #[serde(default)]
     pub video: VideoConfig,
     #[serde(default)]
-    pub audio: AudioConfig,
+    pub audio: MixerAudioConfig,
 }
 
 impl CreateRequest {
@@ -27,6 +29,31 @@ impl CreateRequest {
     }
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct IsoStartRequest {
+    #[serde(flatten)]
+    pub config: IsoConfig,
+    /// Inputs to record, every input including those added later when omitted.
+    #[serde(default)]
+    pub inputs: Option<Vec<String>>,
+}
+
+impl IsoStartRequest {
+    pub fn from_json_body() -> impl Filter<Extract = (Self,), Error = warp::Rejection> + Clone {
+        warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+    }
+}
+
+/// Size requested for a snapshot, when only one is given the other keeps the aspect ratio and
+/// when neither is the frame keeps its size.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct SnapshotQuery {
+    #[serde(default)]
+    pub width: Option<i32>,
+    #[serde(default)]
+    pub height: Option<i32>,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Mixer {
     pub name: String,
@@ -91,6 +118,102 @@ pub async fn debug(
     Ok(warp::reply::with_header(output, "Content-Type", "image/svg+xml").into_response())
 }
 
+/// HTTP Handler for retrieving the recent events of a mixer, oldest first.
+pub async fn events(name: String, mixers: Arc<Mutex<super::Mixers>>) -> JsonResult {
+    let mixers = mixers.lock().await;
+    match mixers.mixers.get(name.as_str()) {
+        Some(mixer) => okay(mixer.events()),
+        None => message_response("Mixer not found", StatusCode::NOT_FOUND),
+    }
+}
+
+/// HTTP Handler for retrieving the tally of every input of a mixer.
+pub async fn tally(name: String, mixers: Arc<Mutex<super::Mixers>>) -> JsonResult {
+    let mixers = mixers.lock().await;
+    match mixers.mixers.get(name.as_str()) {
+        Some(mixer) => okay(mixer.tally()),
+        None => message_response("Mixer not found", StatusCode::NOT_FOUND),
+    }
+}
+
+/// HTTP Handler for retrieving the loudness readings of a mixer's program audio.
+pub async fn loudness(name: String, mixers: Arc<Mutex<super::Mixers>>) -> JsonResult {
+    let mixers = mixers.lock().await;
+    let mixer = match mixers.mixers.get(name.as_str()) {
+        Some(m) => m,
+        None => return message_response("Mixer not found", StatusCode::NOT_FOUND),
+    };
+
+    if mixer.config().audio.loudness.is_none() {
+        return message_response("Loudness measurement is not enabled", StatusCode::NOT_FOUND);
+    }
+
+    okay(mixer.loudness())
+}
+
+/// HTTP Handler for retrieving the manifest of the running ISO recording session of a mixer.
+#[tracing::instrument(skip(mixers))]
+pub async fn iso_get(name: String, mixers: Arc<Mutex<super::Mixers>>) -> JsonResult {
+    let mixers = mixers.lock().await;
+    match mixers.mixers.get(name.as_str()) {
+        Some(mixer) => okay(mixer.iso()),
+        None => message_response("Mixer not found", StatusCode::NOT_FOUND),
+    }
+}
+
+/// HTTP Handler for starting ISO recording of the inputs of a mixer.
+#[tracing::instrument(skip(mixers))]
+pub async fn iso_start(
+    name: String,
+    request: IsoStartRequest,
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> JsonResult {
+    let mut mixers = mixers.lock().await;
+    let mixer = match mixers.mixers.get_mut(name.as_str()) {
+        Some(m) => m,
+        None => return message_response("Mixer not found", StatusCode::NOT_FOUND),
+    };
+
+    match mixer.iso_start(request.config, request.inputs) {
+        Ok(_) => message_response("ISO recording started.", StatusCode::CREATED),
+        Err(e) => error(Error::Mixer(e)),
+    }
+}
+
+/// HTTP Handler for stopping ISO recording of a mixer, responding with the session manifest.
+#[tracing::instrument(skip(mixers))]
+pub async fn iso_stop(name: String, mixers: Arc<Mutex<super::Mixers>>) -> JsonResult {
+    let mut mixers = mixers.lock().await;
+    let mixer = match mixers.mixers.get_mut(name.as_str()) {
+        Some(m) => m,
+        None => return message_response("Mixer not found", StatusCode::NOT_FOUND),
+    };
+
+    match mixer.iso_stop() {
+        Ok(manifest) => okay(manifest),
+        Err(e) => error(Error::Mixer(e)),
+    }
+}
+
+/// HTTP Handler for retrieving the latest frame of the program of a mixer as an image.
+#[tracing::instrument(skip(mixers))]
+pub async fn snapshot(
+    name: String,
+    format: ImageFormat,
+    query: SnapshotQuery,
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> Result<reply::Response, Rejection> {
+    let sample = match mixers.lock().await.mixers.get(name.as_str()) {
+        Some(mixer) => mixer.snapshot(),
+        None => return Err(reject::custom(Error::NotFound)),
+    };
+
+    match sample {
+        Ok(sample) => snapshot_response(sample, format, query).await,
+        Err(e) => Err(reject::custom(Error::Mixer(e))),
+    }
+}
+
 pub async fn list(mixers: Arc<Mutex<super::Mixers>>) -> JsonResult {
     let mixers: Vec<Mixer> = mixers
         .lock()
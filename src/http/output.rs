@@ -1,26 +1,39 @@
-use super::{error, message_response, okay, Error, JsonResult};
+use super::{auth::Role, error, message_response, okay, Error, JsonResult};
 use crate::{
+    mixer::{Error as MixerError, Mixer},
     output::{Config as OutputConfig, EncoderConfig, Output as MixerOutput},
-    AudioConfig, VideoConfig,
+    secret::SecretUrl,
+    AudioConfig, MultiviewerConfig, SegmentConfig, VideoConfig,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use warp::{http::StatusCode, Filter};
 
+/// Output types sending to destinations outside of the switcher or writing files on the server,
+/// which only admins may manage.
+const ADMIN_TYPES: [&str; 4] = ["RTMP", "Icecast", "File", "AudioFile"];
+
 /// HTTP Request for creating a new [`output::Output`](../input/struct.Output.html)
 /// to be used by the [`mixer`](../mixer/struct.Mixer.html).
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct CreateRequest {
     pub name: String,
     pub output_type: String,
-    pub location: String,
+    pub location: SecretUrl,
     #[serde(default)]
     pub audio: AudioConfig,
+    /// Defaults to the video settings of the mixer.
     #[serde(default)]
-    pub video: VideoConfig,
+    pub video: Option<VideoConfig>,
     #[serde(default)]
     pub encoder: EncoderConfig,
+    /// Splitting of `File` recordings into segments.
+    #[serde(default)]
+    pub segment: SegmentConfig,
+    /// Settings of `Multiviewer` outputs, which are sent to an output of `sink_type`.
+    #[serde(default)]
+    pub multiviewer: MultiviewerConfig,
 }
 
 impl CreateRequest {
@@ -31,6 +44,111 @@ impl CreateRequest {
         // (and to reject huge payloads)...
         warp::body::content_length_limit(1024 * 16).and(warp::body::json())
     }
+
+    /// Returns whether the output sends to a destination outside of the switcher or writes to a
+    /// file.
+    pub fn admin_only(&self) -> bool {
+        let output_type = if self.output_type == "Multiviewer" {
+            &self.multiviewer.sink_type
+        } else {
+            &self.output_type
+        };
+        ADMIN_TYPES.contains(&output_type.as_str())
+    }
+
+    /// Creates the [`output::Output`](../output/struct.Output.html) described by this request.
+    /// Without video settings of its own the output uses `mixer_video`, those of its mixer.
+    pub fn create(self, mixer_video: &VideoConfig) -> Result<MixerOutput, Error> {
+        if self.output_type == "Multiviewer" {
+            return self.create_multiviewer(mixer_video);
+        }
+
+        let config = OutputConfig {
+            name: self.name.clone(),
+            video: self.video.unwrap_or_else(|| mixer_video.clone()),
+            audio: self.audio,
+            encoder: self.encoder,
+            mux: None,
+        };
+
+        match self.output_type.as_str() {
+            "RTMP" => {
+                MixerOutput::create_rtmp(config, self.location.reveal()).map_err(Error::Mixer)
+            }
+            "Fake" => MixerOutput::create_fake(config).map_err(Error::Mixer),
+            "Auto" => MixerOutput::create_auto(config).map_err(Error::Mixer),
+            "File" => MixerOutput::create_file(config, self.location.reveal(), &self.segment)
+                .map_err(Error::Mixer),
+            "Icecast" => {
+                MixerOutput::create_icecast(config, self.location.reveal()).map_err(Error::Mixer)
+            }
+            "AudioFile" => {
+                MixerOutput::create_audio_file(config, self.location.reveal()).map_err(Error::Mixer)
+            }
+            _ => Err(Error::Unknown),
+        }
+    }
+
+    fn create_multiviewer(self, mixer_video: &VideoConfig) -> Result<MixerOutput, Error> {
+        if self.multiviewer.sink_type == "Multiviewer" {
+            return Err(Error::Mixer(MixerError::InvalidConfig(
+                "a multiviewer cannot be sent to another multiviewer".to_string(),
+            )));
+        }
+
+        let sink = CreateRequest {
+            name: format!("{}_sink", self.name),
+            output_type: self.multiviewer.sink_type.clone(),
+            ..self.clone()
+        }
+        .create(mixer_video)?;
+        let config = OutputConfig {
+            name: self.name,
+            video: self.video.unwrap_or_else(|| mixer_video.clone()),
+            audio: self.audio,
+            encoder: self.encoder,
+            mux: None,
+        };
+
+        MixerOutput::create_multiviewer(config, sink, &self.multiviewer).map_err(Error::Mixer)
+    }
+}
+
+/// Recording action applied to a `File` [`output::Output`](../output/struct.Output.html).
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub enum RecordingAction {
+    /// Starts a new recording, written to `location` when given.
+    Start,
+    /// Ends the recording, finalizing its file.
+    Stop,
+    /// Closes the current file and continues the recording in a new one.
+    Split,
+}
+
+/// HTTP Request for controlling the recording of a `File`
+/// [`output::Output`](../output/struct.Output.html)
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RecordingRequest {
+    pub action: RecordingAction,
+    #[serde(default)]
+    pub location: Option<String>,
+}
+
+impl RecordingRequest {
+    /// Constructs a new `RecordingRequest` from a json body.
+    /// This function consumes the http request body through warp::body::json().
+    pub fn from_json_body() -> impl Filter<Extract = (Self,), Error = warp::Rejection> + Clone {
+        // When accepting a body, we want a JSON body
+        // (and to reject huge payloads)...
+        warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+    }
+}
+
+/// HTTP Response for the recording of a `File` [`output::Output`](../output/struct.Output.html)
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Recording {
+    pub recording: bool,
+    pub location: String,
 }
 
 /// HTTP Response for a [`output::Output`](../input/struct.Output.html)
@@ -38,6 +156,13 @@ impl CreateRequest {
 pub struct Output {
     pub name: String,
     pub output_type: String,
+    pub location: SecretUrl,
+}
+
+/// HTTP Response revealing the full location of an input or output, including its secrets.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RevealedLocation {
+    pub name: String,
     pub location: String,
 }
 
@@ -70,26 +195,20 @@ pub async fn list(mixer_name: String, mixers: Arc<Mutex<super::Mixers>>) -> Json
 pub async fn add(
     mixer_name: String,
     output: CreateRequest,
+    role: Role,
     mixers: Arc<Mutex<super::Mixers>>,
 ) -> JsonResult {
-    let mut mixers = mixers.lock().await;
-
-    let config = OutputConfig {
-        name: output.name.clone(),
-        video: output.video,
-        audio: output.audio,
-        encoder: output.encoder,
-        mux: None,
-    };
+    if output.admin_only() && role < Role::Admin {
+        return error(Error::Forbidden);
+    }
 
-    let output = match output.output_type.as_str() {
-        "RTMP" => MixerOutput::create_rtmp(config, &output.location).map_err(super::Error::Mixer),
-        "Fake" => MixerOutput::create_fake(config).map_err(super::Error::Mixer),
-        "Auto" => MixerOutput::create_auto(config).map_err(super::Error::Mixer),
-        _ => Err(super::Error::Unknown),
+    let mut mixers = mixers.lock().await;
+    let video = match mixers.mixers.get(&mixer_name) {
+        Some(mixer) => mixer.config().video,
+        None => return error(Error::NotFound),
     };
 
-    let output = match output {
+    let output = match output.create(&video) {
         Err(e) => return error(e),
         Ok(i) => i,
     };
@@ -128,12 +247,38 @@ pub async fn get(
     okay(&output)
 }
 
+/// HTTP Handler for revealing the location of an [`output::Output`](../output/struct.Output.html)
+/// including its stream key or password. Only admins may use it.
+#[tracing::instrument(skip(mixers))]
+pub async fn reveal(
+    mixer_name: String,
+    output_name: String,
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> JsonResult {
+    let mixers = mixers.lock().await;
+    let output = match mixers
+        .mixers
+        .get(&mixer_name)
+        .and_then(|mixer| mixer.outputs.get(output_name.as_str()))
+    {
+        None => return error(Error::NotFound),
+        Some(output) => output,
+    };
+
+    tracing::info!("Revealing location of output '{}'", output_name);
+    okay(RevealedLocation {
+        name: output.name(),
+        location: output.location().reveal().to_string(),
+    })
+}
+
 /// HTTP Handler for removing an [`output::Output`](../output/struct.Output.html) from the associated
 /// mixer.
 #[tracing::instrument(skip(mixers))]
 pub async fn remove(
     mixer_name: String,
     output_name: String,
+    role: Role,
     mixers: Arc<Mutex<super::Mixers>>,
 ) -> JsonResult {
     let mut mixers = mixers.lock().await;
@@ -142,8 +287,85 @@ pub async fn remove(
         Some(mixer) => mixer,
     };
 
-    match mixer.output_remove(&output_name) {
+    let admin_only = mixer.outputs.get(&output_name).is_some_and(admin_only);
+    if admin_only && role < Role::Admin {
+        return error(Error::Forbidden);
+    }
+
+    match remove_output(mixer, &output_name) {
         Ok(_) => message_response("Output removed", StatusCode::OK),
         Err(e) => error(Error::Mixer(e)),
     }
 }
+
+/// HTTP Handler for starting, stopping or splitting the recording of a `File`
+/// [`output::Output`](../output/struct.Output.html) without interrupting the program.
+#[tracing::instrument(skip(mixers))]
+pub async fn recording(
+    mixer_name: String,
+    output_name: String,
+    request: RecordingRequest,
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> JsonResult {
+    let mut mixers = mixers.lock().await;
+    let mixer = match mixers.mixers.get_mut(&mixer_name) {
+        None => return error(Error::NotFound),
+        Some(mixer) => mixer,
+    };
+
+    match control_recording(
+        mixer,
+        &output_name,
+        request.action,
+        request.location.as_deref(),
+    ) {
+        Ok(recording) => okay(recording),
+        Err(e) => error(e),
+    }
+}
+
+/// Starts, stops or splits the recording of a `File` output of a mixer.
+pub(crate) fn control_recording(
+    mixer: &mut Mixer,
+    name: &str,
+    action: RecordingAction,
+    location: Option<&str>,
+) -> Result<Recording, Error> {
+    let output = match mixer.outputs.get_mut(name) {
+        Some(MixerOutput::File(output)) => output,
+        Some(_) => {
+            return Err(Error::Mixer(MixerError::InvalidConfig(
+                "the output does not support recording".to_string(),
+            )))
+        }
+        None => return Err(Error::NotFound),
+    };
+
+    match action {
+        RecordingAction::Start => output.start_recording(location)?,
+        RecordingAction::Stop => output.stop_recording()?,
+        RecordingAction::Split => output.split()?,
+    };
+
+    Ok(Recording {
+        recording: output.recording(),
+        location: output.location.clone(),
+    })
+}
+
+/// Removes an output from its mixer. Recordings wait for their file to be finalized, which blocks,
+/// so the runtime moves its other tasks off this thread meanwhile.
+pub(crate) fn remove_output(mixer: &mut Mixer, name: &str) -> Result<(), MixerError> {
+    match mixer.outputs.get(name) {
+        Some(MixerOutput::File(_)) => tokio::task::block_in_place(|| mixer.output_remove(name)),
+        _ => mixer.output_remove(name),
+    }
+}
+
+/// Returns whether an output sends to a destination outside of the switcher or writes to a file.
+fn admin_only(output: &MixerOutput) -> bool {
+    match output {
+        MixerOutput::Multiviewer(multiviewer) => admin_only(&multiviewer.sink),
+        output => ADMIN_TYPES.contains(&output.output_type().as_str()),
+    }
+}
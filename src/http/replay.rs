@@ -0,0 +1,94 @@
+use super::{error, message_response, okay, Error, JsonResult};
+use crate::input::{replay::ReplayStatus, Input as MixerInput};
+
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::Mutex;
+use warp::{http::StatusCode, Filter};
+
+/// HTTP Request for playing back a segment of a replay
+/// [`input::Input`](../input/struct.Input.html).
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PlayRequest {
+    /// Name of the replay input.
+    pub input: String,
+    /// Start of the segment, in milliseconds before the most recent buffered frame.
+    pub mark_in_ms: u64,
+    /// End of the segment, in milliseconds before the most recent buffered frame.
+    #[serde(default)]
+    pub mark_out_ms: u64,
+    /// Playback speed, below 1 for slow motion.
+    #[serde(default = "PlayRequest::speed_default")]
+    pub speed: f64,
+    /// Sets the replay input active once playback starts.
+    #[serde(default)]
+    pub take: bool,
+}
+
+impl PlayRequest {
+    fn speed_default() -> f64 {
+        1.0
+    }
+
+    /// Constructs a new `PlayRequest` from a json body.
+    /// This function consumes the http request body through warp::body::json().
+    pub fn from_json_body() -> impl Filter<Extract = (Self,), Error = warp::Rejection> + Clone {
+        // When accepting a body, we want a JSON body
+        // (and to reject huge payloads)...
+        warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+    }
+}
+
+/// HTTP Handler for listing the buffers of the replay inputs of a mixer.
+#[tracing::instrument(skip(mixers))]
+pub async fn list(mixer_name: String, mixers: Arc<Mutex<super::Mixers>>) -> JsonResult {
+    let mixers = mixers.lock().await;
+    let mixer = match mixers.mixers.get(&mixer_name) {
+        None => return error(Error::NotFound),
+        Some(mixer) => mixer,
+    };
+
+    let replays: HashMap<String, ReplayStatus> = mixer
+        .inputs
+        .iter()
+        .filter_map(|(name, input)| match input {
+            MixerInput::Replay(replay) => Some((name.clone(), replay.status())),
+            _ => None,
+        })
+        .collect();
+    okay(replays)
+}
+
+/// HTTP Handler for playing back a marked segment of a replay
+/// [`input::Input`](../input/struct.Input.html).
+#[tracing::instrument(skip(mixers))]
+pub async fn play(
+    mixer_name: String,
+    request: PlayRequest,
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> JsonResult {
+    let mut mixers = mixers.lock().await;
+    let mixer = match mixers.mixers.get_mut(&mixer_name) {
+        None => return error(Error::NotFound),
+        Some(mixer) => mixer,
+    };
+
+    let result = match mixer.inputs.get(request.input.as_str()) {
+        Some(MixerInput::Replay(replay)) => {
+            replay.play(request.mark_in_ms, request.mark_out_ms, request.speed)
+        }
+        Some(_) => return message_response("Input is not a replay", StatusCode::BAD_REQUEST),
+        None => return error(Error::NotFound),
+    };
+    if let Err(e) = result {
+        return error(Error::Mixer(e));
+    }
+
+    if request.take {
+        if let Err(e) = mixer.input_set_active(&request.input) {
+            return error(Error::Mixer(e));
+        }
+    }
+
+    message_response("Replay started", StatusCode::OK)
+}
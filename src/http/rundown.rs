@@ -0,0 +1,296 @@
+use super::{
+    action::{self, Action},
+    auth::Role,
+    error, okay, Error, JsonResult,
+};
+use crate::mixer::{Error as MixerError, Mixer};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, sync::Arc, time::Duration};
+use tokio::sync::Mutex;
+use warp::Filter;
+
+/// How often the scheduler checks for due cues.
+const SCHEDULER_INTERVAL_MS: u64 = 250;
+
+/// Execution state of a [`Cue`](struct.Cue.html).
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Default)]
+pub enum CueState {
+    #[default]
+    Pending,
+    Done,
+    Failed,
+    /// The cue came due while the rundown was on hold, or had already passed when it was saved.
+    Skipped,
+}
+
+/// A timed [`Action`](../action/enum.Action.html) in a mixer's [`Rundown`](struct.Rundown.html).
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Cue {
+    pub at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub action: Action,
+    #[serde(skip_deserializing)]
+    pub state: CueState,
+    #[serde(skip_deserializing)]
+    pub error: Option<String>,
+}
+
+/// Timed cues executed against a mixer by the server's scheduler.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct Rundown {
+    pub cues: Vec<Cue>,
+    /// Cues are not executed while the rundown is on hold.
+    pub hold: bool,
+}
+
+impl Rundown {
+    /// Replaces the cues of the rundown, ordered by time. Cues which are already due are skipped
+    /// rather than all firing at once on the next check.
+    pub fn set_cues(&mut self, mut cues: Vec<Cue>, now: DateTime<Utc>) {
+        cues.sort_by_key(|cue| cue.at);
+        self.cues = cues;
+        for index in self.due(now) {
+            self.cues[index].state = CueState::Skipped;
+        }
+    }
+
+    /// Puts the rundown on hold or releases it. Cues which came due during the hold are skipped
+    /// rather than all firing at once on release.
+    pub fn set_hold(&mut self, hold: bool, now: DateTime<Utc>) {
+        if self.hold && !hold {
+            for index in self.due(now) {
+                self.cues[index].state = CueState::Skipped;
+            }
+        }
+        self.hold = hold;
+    }
+
+    /// Returns the indices of the pending cues which are due.
+    fn due(&self, now: DateTime<Utc>) -> Vec<usize> {
+        self.cues
+            .iter()
+            .enumerate()
+            .filter(|(_, cue)| cue.state == CueState::Pending && cue.at <= now)
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
+/// Problem found with a cue during a dry-run.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Problem {
+    pub cue: usize,
+    pub at: DateTime<Utc>,
+    pub message: String,
+}
+
+/// HTTP Response for a rundown dry-run.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct DryRun {
+    pub valid: bool,
+    pub problems: Vec<Problem>,
+}
+
+/// Walks the cues in order and checks every input and output they reference exists on the mixer,
+/// or is added by an earlier cue, at the time the cue runs.
+pub fn dry_run(mixer: &Mixer, cues: &[Cue]) -> Vec<Problem> {
+    let mut inputs: HashSet<String> = mixer.inputs.keys().cloned().collect();
+    let mut outputs: HashSet<String> = mixer.outputs.keys().cloned().collect();
+    let mut problems = Vec::new();
+
+    for (index, cue) in cues.iter().enumerate() {
+        if let Some(message) = cue.action.check(&mut inputs, &mut outputs) {
+            problems.push(Problem {
+                cue: index,
+                at: cue.at,
+                message,
+            });
+        }
+    }
+
+    problems
+}
+
+/// Executes the cues of every rundown as they come due. Runs until the server exits.
+pub async fn scheduler(mixers: Arc<Mutex<super::Mixers>>) {
+    loop {
+        tokio::time::delay_for(Duration::from_millis(SCHEDULER_INTERVAL_MS)).await;
+        run_due(&mixers).await;
+    }
+}
+
+async fn run_due(mixers: &Arc<Mutex<super::Mixers>>) {
+    let now = Utc::now();
+    let mut crossfades = Vec::new();
+
+    {
+        let mut guard = mixers.lock().await;
+        let names: Vec<String> = guard.rundowns.keys().cloned().collect();
+
+        for name in names {
+            let due: Vec<(usize, Action)> = match guard.rundowns.get(&name) {
+                Some(rundown) if !rundown.hold => rundown
+                    .due(now)
+                    .into_iter()
+                    .map(|index| (index, rundown.cues[index].action.clone()))
+                    .collect(),
+                _ => continue,
+            };
+
+            for (index, action) in due {
+                tracing::info!("running cue {} of mixer '{}': {:?}", index, name, action);
+
+                let crossfade = action.crossfade();
+                let result = action.execute(&mut guard, &name);
+                if let (Ok(()), Some((input, duration_ms))) = (&result, crossfade) {
+                    crossfades.push((name.clone(), input, duration_ms));
+                }
+                if let Some(cue) = guard
+                    .rundowns
+                    .get_mut(&name)
+                    .and_then(|rundown| rundown.cues.get_mut(index))
+                {
+                    match result {
+                        Ok(()) => cue.state = CueState::Done,
+                        Err(e) => {
+                            tracing::error!("cue {} of mixer '{}' failed: {}", index, name, e);
+                            cue.state = CueState::Failed;
+                            cue.error = Some(e.to_string());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    for (mixer, input, duration_ms) in crossfades {
+        tokio::spawn(action::crossfade(
+            Arc::clone(mixers),
+            mixer,
+            input,
+            duration_ms,
+        ));
+    }
+}
+
+/// HTTP Request for replacing the cues of a mixer's rundown, or dry-running them.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct UpdateRequest {
+    pub cues: Vec<Cue>,
+}
+
+impl UpdateRequest {
+    /// Constructs a new `UpdateRequest` from a json body.
+    /// This function consumes the http request body through warp::body::json().
+    pub fn from_json_body() -> impl Filter<Extract = (Self,), Error = warp::Rejection> + Clone {
+        // When accepting a body, we want a JSON body
+        // (and to reject huge payloads)...
+        warp::body::content_length_limit(1024 * 64).and(warp::body::json())
+    }
+}
+
+/// HTTP Request for putting a mixer's rundown on hold or releasing it.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct HoldRequest {
+    pub hold: bool,
+}
+
+impl HoldRequest {
+    /// Constructs a new `HoldRequest` from a json body.
+    /// This function consumes the http request body through warp::body::json().
+    pub fn from_json_body() -> impl Filter<Extract = (Self,), Error = warp::Rejection> + Clone {
+        // When accepting a body, we want a JSON body
+        // (and to reject huge payloads)...
+        warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+    }
+}
+
+/// HTTP Handler for retrieving the rundown of a mixer.
+#[tracing::instrument(skip(mixers))]
+pub async fn get(mixer_name: String, mixers: Arc<Mutex<super::Mixers>>) -> JsonResult {
+    let mixers = mixers.lock().await;
+    if !mixers.mixers.contains_key(&mixer_name) {
+        return error(Error::NotFound);
+    }
+
+    okay(
+        mixers
+            .rundowns
+            .get(&mixer_name)
+            .cloned()
+            .unwrap_or_default(),
+    )
+}
+
+/// HTTP Handler for replacing the cues of a mixer's rundown.
+#[tracing::instrument(skip(mixers))]
+pub async fn update(
+    mixer_name: String,
+    request: UpdateRequest,
+    role: Role,
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> JsonResult {
+    if request
+        .cues
+        .iter()
+        .any(|cue| cue.action.required_role() > role)
+    {
+        return error(Error::Forbidden);
+    }
+    if request.cues.iter().any(|cue| cue.action.redacted()) {
+        return error(Error::Mixer(MixerError::InvalidConfig(
+            "locations of cues must not be redacted".to_string(),
+        )));
+    }
+
+    let mut mixers = mixers.lock().await;
+    if !mixers.mixers.contains_key(&mixer_name) {
+        return error(Error::NotFound);
+    }
+
+    let rundown = mixers.rundowns.entry(mixer_name).or_default();
+    rundown.set_cues(request.cues, Utc::now());
+    okay(&*rundown)
+}
+
+/// HTTP Handler for putting a mixer's rundown on hold or releasing it.
+#[tracing::instrument(skip(mixers))]
+pub async fn hold(
+    mixer_name: String,
+    request: HoldRequest,
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> JsonResult {
+    let mut mixers = mixers.lock().await;
+    if !mixers.mixers.contains_key(&mixer_name) {
+        return error(Error::NotFound);
+    }
+
+    let rundown = mixers.rundowns.entry(mixer_name).or_default();
+    rundown.set_hold(request.hold, Utc::now());
+    okay(&*rundown)
+}
+
+/// HTTP Handler for validating cues against a mixer without storing them.
+#[tracing::instrument(skip(mixers))]
+pub async fn dry_run_cues(
+    mixer_name: String,
+    request: UpdateRequest,
+    mixers: Arc<Mutex<super::Mixers>>,
+) -> JsonResult {
+    let mixers = mixers.lock().await;
+    let mixer = match mixers.mixers.get(&mixer_name) {
+        None => return error(Error::NotFound),
+        Some(mixer) => mixer,
+    };
+
+    let mut cues = request.cues;
+    cues.sort_by_key(|cue| cue.at);
+    let problems = dry_run(mixer, &cues);
+
+    okay(DryRun {
+        valid: problems.is_empty(),
+        problems,
+    })
+}
@@ -1,15 +1,27 @@
+pub mod audio;
 pub mod fake;
+pub mod playlist;
+pub mod replay;
 pub mod test;
 pub mod uri;
 
-use crate::{mixer::Error as MixerError, AudioConfig, Result, VideoConfig};
+use crate::secret::SecretUrl;
+use crate::{
+    mixer::Error as MixerError, AudioConfig, AudioMode, BarColor, ChromaKeyConfig, ColorConfig,
+    CompressorConfig, CropConfig, EqualizerConfig, MixerAudioConfig, NoiseGateConfig,
+    PlaybackConfig, ReplayConfig, Result, ScalingMode, VideoConfig, VideoFlip, VideoRotation,
+};
+pub use audio::Audio;
 pub use fake::Fake;
+pub use playlist::Playlist;
+pub use replay::Replay;
 use serde::{Deserialize, Serialize};
 pub use test::Test;
 pub use uri::URI;
 
 use gst::prelude::*;
 use gstreamer as gst;
+use std::sync::Mutex;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Config {
@@ -17,12 +29,17 @@ pub struct Config {
     pub video: VideoConfig,
     pub audio: AudioConfig,
     pub record: bool,
+    #[serde(default)]
+    pub playback: PlaybackConfig,
 }
 
 pub enum Input {
     URI(URI),
     Test(Test),
     Fake(Fake),
+    Audio(Audio),
+    Playlist(Playlist),
+    Replay(Replay),
 }
 
 impl Input {
@@ -38,19 +55,43 @@ impl Input {
         Fake::create(config).map(Self::Fake)
     }
 
+    pub fn create_audio(config: Config, uri: &str, looping: bool) -> Result<Self> {
+        Audio::create(config, uri, looping).map(Self::Audio)
+    }
+
+    pub fn create_playlist(
+        config: Config,
+        uris: Vec<String>,
+        shuffle: bool,
+        looping: bool,
+    ) -> Result<Self> {
+        Playlist::create(config, uris, shuffle, looping).map(Self::Playlist)
+    }
+
+    pub fn create_replay(config: Config, source: &str, replay: ReplayConfig) -> Result<Self> {
+        Replay::create(config, source, replay).map(Self::Replay)
+    }
+
     pub fn name(&self) -> String {
         match self {
             Input::URI(input) => input.name(),
             Input::Test(input) => input.name(),
             Input::Fake(input) => input.name(),
+            Input::Audio(input) => input.name(),
+            Input::Playlist(input) => input.name(),
+            Input::Replay(input) => input.name(),
         }
     }
 
-    pub fn location(&self) -> String {
+    /// Returns where the input is read from, which may hold a stream key or password.
+    pub fn location(&self) -> SecretUrl {
         match self {
-            Input::URI(input) => input.location.clone(),
-            Input::Test(_) => "".to_string(),
-            Input::Fake(_) => "".to_string(),
+            Input::URI(input) => SecretUrl::new(input.location.as_str()),
+            Input::Test(_) => SecretUrl::default(),
+            Input::Fake(_) => SecretUrl::default(),
+            Input::Audio(input) => SecretUrl::new(input.location.as_str()),
+            Input::Playlist(input) => SecretUrl::new(input.items().join(",")),
+            Input::Replay(input) => SecretUrl::new(input.source.as_str()),
         }
     }
 
@@ -59,6 +100,9 @@ impl Input {
             Input::URI(_) => "URI".to_string(),
             Input::Test(_) => "Test".to_string(),
             Input::Fake(_) => "Fake".to_string(),
+            Input::Audio(_) => "Audio".to_string(),
+            Input::Playlist(_) => "Playlist".to_string(),
+            Input::Replay(_) => "Replay".to_string(),
         }
     }
 
@@ -72,6 +116,9 @@ impl Input {
             Input::URI(input) => input.link(pipeline, audio, video),
             Input::Test(input) => input.link(pipeline, audio, video),
             Input::Fake(input) => input.link(pipeline, audio, video),
+            Input::Audio(input) => input.link(pipeline, audio, video),
+            Input::Playlist(input) => input.link(pipeline, audio, video),
+            Input::Replay(input) => input.link(pipeline, audio, video),
         }
     }
 
@@ -80,6 +127,9 @@ impl Input {
             Input::URI(input) => input.unlink(),
             Input::Test(input) => input.unlink(),
             Input::Fake(input) => input.unlink(),
+            Input::Audio(input) => input.unlink(),
+            Input::Playlist(input) => input.unlink(),
+            Input::Replay(input) => input.unlink(),
         }
     }
 
@@ -88,6 +138,9 @@ impl Input {
             Input::URI(input) => input.set_state(state),
             Input::Test(input) => input.set_state(state),
             Input::Fake(input) => input.set_state(state),
+            Input::Audio(input) => input.set_state(state),
+            Input::Playlist(input) => input.set_state(state),
+            Input::Replay(input) => input.set_state(state),
         }
     }
 
@@ -96,6 +149,106 @@ impl Input {
             Input::URI(input) => input.set_volume(volume, update_config),
             Input::Test(input) => input.set_volume(volume, update_config),
             Input::Fake(input) => input.set_volume(volume, update_config),
+            Input::Audio(input) => input.set_volume(volume, update_config),
+            Input::Playlist(input) => input.set_volume(volume, update_config),
+            Input::Replay(input) => input.set_volume(volume, update_config),
+        }
+    }
+
+    pub fn set_mute(&mut self, mute: bool, update_config: bool) -> Result<()> {
+        match self {
+            Input::URI(input) => input.set_mute(mute, update_config),
+            Input::Test(input) => input.set_mute(mute, update_config),
+            Input::Fake(input) => input.set_mute(mute, update_config),
+            Input::Audio(input) => input.set_mute(mute, update_config),
+            Input::Playlist(input) => input.set_mute(mute, update_config),
+            Input::Replay(input) => input.set_mute(mute, update_config),
+        }
+    }
+
+    pub fn set_gain(&mut self, gain: f64, update_config: bool) -> Result<()> {
+        match self {
+            Input::URI(input) => input.set_gain(gain, update_config),
+            Input::Test(input) => input.set_gain(gain, update_config),
+            Input::Fake(input) => input.set_gain(gain, update_config),
+            Input::Audio(input) => input.set_gain(gain, update_config),
+            Input::Playlist(input) => input.set_gain(gain, update_config),
+            Input::Replay(input) => input.set_gain(gain, update_config),
+        }
+    }
+
+    pub fn set_equalizer(&mut self, equalizer: EqualizerConfig, update_config: bool) -> Result<()> {
+        match self {
+            Input::URI(input) => input.set_equalizer(equalizer, update_config),
+            Input::Test(input) => input.set_equalizer(equalizer, update_config),
+            Input::Fake(input) => input.set_equalizer(equalizer, update_config),
+            Input::Audio(input) => input.set_equalizer(equalizer, update_config),
+            Input::Playlist(input) => input.set_equalizer(equalizer, update_config),
+            Input::Replay(input) => input.set_equalizer(equalizer, update_config),
+        }
+    }
+
+    pub fn set_compressor(
+        &mut self,
+        compressor: Option<CompressorConfig>,
+        update_config: bool,
+    ) -> Result<()> {
+        match self {
+            Input::URI(input) => input.set_compressor(compressor, update_config),
+            Input::Test(input) => input.set_compressor(compressor, update_config),
+            Input::Fake(input) => input.set_compressor(compressor, update_config),
+            Input::Audio(input) => input.set_compressor(compressor, update_config),
+            Input::Playlist(input) => input.set_compressor(compressor, update_config),
+            Input::Replay(input) => input.set_compressor(compressor, update_config),
+        }
+    }
+
+    pub fn set_noise_gate(
+        &mut self,
+        noise_gate: Option<NoiseGateConfig>,
+        update_config: bool,
+    ) -> Result<()> {
+        match self {
+            Input::URI(input) => input.set_noise_gate(noise_gate, update_config),
+            Input::Test(input) => input.set_noise_gate(noise_gate, update_config),
+            Input::Fake(input) => input.set_noise_gate(noise_gate, update_config),
+            Input::Audio(input) => input.set_noise_gate(noise_gate, update_config),
+            Input::Playlist(input) => input.set_noise_gate(noise_gate, update_config),
+            Input::Replay(input) => input.set_noise_gate(noise_gate, update_config),
+        }
+    }
+
+    pub fn set_av_offset(&mut self, av_offset_ms: i64, update_config: bool) -> Result<()> {
+        match self {
+            Input::URI(input) => input.set_av_offset(av_offset_ms, update_config),
+            Input::Test(input) => input.set_av_offset(av_offset_ms, update_config),
+            Input::Fake(input) => input.set_av_offset(av_offset_ms, update_config),
+            Input::Audio(input) => input.set_av_offset(av_offset_ms, update_config),
+            Input::Playlist(input) => input.set_av_offset(av_offset_ms, update_config),
+            Input::Replay(input) => input.set_av_offset(av_offset_ms, update_config),
+        }
+    }
+
+    pub fn set_audio_mode(&mut self, mode: AudioMode, update_config: bool) -> Result<()> {
+        match self {
+            Input::URI(input) => input.set_audio_mode(mode, update_config),
+            Input::Test(input) => input.set_audio_mode(mode, update_config),
+            Input::Fake(input) => input.set_audio_mode(mode, update_config),
+            Input::Audio(input) => input.set_audio_mode(mode, update_config),
+            Input::Playlist(input) => input.set_audio_mode(mode, update_config),
+            Input::Replay(input) => input.set_audio_mode(mode, update_config),
+        }
+    }
+
+    /// Converts the input's audio to the sample rate, channels and format of `audio`.
+    pub fn set_audio_format(&mut self, audio: &MixerAudioConfig) -> Result<()> {
+        match self {
+            Input::URI(input) => input.set_audio_format(audio),
+            Input::Test(input) => input.set_audio_format(audio),
+            Input::Fake(input) => input.set_audio_format(audio),
+            Input::Audio(input) => input.set_audio_format(audio),
+            Input::Playlist(input) => input.set_audio_format(audio),
+            Input::Replay(input) => input.set_audio_format(audio),
         }
     }
 
@@ -104,6 +257,9 @@ impl Input {
             Input::URI(input) => input.set_zorder(zorder, update_config),
             Input::Test(input) => input.set_zorder(zorder, update_config),
             Input::Fake(input) => input.set_zorder(zorder, update_config),
+            Input::Audio(input) => input.set_zorder(zorder, update_config),
+            Input::Playlist(input) => input.set_zorder(zorder, update_config),
+            Input::Replay(input) => input.set_zorder(zorder, update_config),
         }
     }
 
@@ -112,6 +268,9 @@ impl Input {
             Input::URI(input) => input.set_width(width, update_config),
             Input::Test(input) => input.set_width(width, update_config),
             Input::Fake(input) => input.set_width(width, update_config),
+            Input::Audio(input) => input.set_width(width, update_config),
+            Input::Playlist(input) => input.set_width(width, update_config),
+            Input::Replay(input) => input.set_width(width, update_config),
         }
     }
 
@@ -120,6 +279,9 @@ impl Input {
             Input::URI(input) => input.set_height(height, update_config),
             Input::Test(input) => input.set_height(height, update_config),
             Input::Fake(input) => input.set_height(height, update_config),
+            Input::Audio(input) => input.set_height(height, update_config),
+            Input::Playlist(input) => input.set_height(height, update_config),
+            Input::Replay(input) => input.set_height(height, update_config),
         }
     }
 
@@ -128,6 +290,9 @@ impl Input {
             Input::URI(input) => input.set_xpos(xpos, update_config),
             Input::Test(input) => input.set_xpos(xpos, update_config),
             Input::Fake(input) => input.set_xpos(xpos, update_config),
+            Input::Audio(input) => input.set_xpos(xpos, update_config),
+            Input::Playlist(input) => input.set_xpos(xpos, update_config),
+            Input::Replay(input) => input.set_xpos(xpos, update_config),
         }
     }
 
@@ -136,6 +301,9 @@ impl Input {
             Input::URI(input) => input.set_ypos(ypos, update_config),
             Input::Test(input) => input.set_ypos(ypos, update_config),
             Input::Fake(input) => input.set_ypos(ypos, update_config),
+            Input::Audio(input) => input.set_ypos(ypos, update_config),
+            Input::Playlist(input) => input.set_ypos(ypos, update_config),
+            Input::Replay(input) => input.set_ypos(ypos, update_config),
         }
     }
 
@@ -144,6 +312,143 @@ impl Input {
             Input::URI(input) => input.set_alpha(alpha, update_config),
             Input::Test(input) => input.set_alpha(alpha, update_config),
             Input::Fake(input) => input.set_alpha(alpha, update_config),
+            Input::Audio(input) => input.set_alpha(alpha, update_config),
+            Input::Playlist(input) => input.set_alpha(alpha, update_config),
+            Input::Replay(input) => input.set_alpha(alpha, update_config),
+        }
+    }
+
+    pub fn set_chroma_key(
+        &mut self,
+        chroma_key: Option<ChromaKeyConfig>,
+        update_config: bool,
+    ) -> Result<()> {
+        match self {
+            Input::URI(input) => input.set_chroma_key(chroma_key, update_config),
+            Input::Test(input) => input.set_chroma_key(chroma_key, update_config),
+            Input::Fake(input) => input.set_chroma_key(chroma_key, update_config),
+            Input::Audio(input) => input.set_chroma_key(chroma_key, update_config),
+            Input::Playlist(input) => input.set_chroma_key(chroma_key, update_config),
+            Input::Replay(input) => input.set_chroma_key(chroma_key, update_config),
+        }
+    }
+
+    pub fn set_crop(&mut self, crop: CropConfig, update_config: bool) -> Result<()> {
+        match self {
+            Input::URI(input) => input.set_crop(crop, update_config),
+            Input::Test(input) => input.set_crop(crop, update_config),
+            Input::Fake(input) => input.set_crop(crop, update_config),
+            Input::Audio(input) => input.set_crop(crop, update_config),
+            Input::Playlist(input) => input.set_crop(crop, update_config),
+            Input::Replay(input) => input.set_crop(crop, update_config),
+        }
+    }
+
+    pub fn set_flip(&mut self, flip: Option<VideoFlip>, update_config: bool) -> Result<()> {
+        match self {
+            Input::URI(input) => input.set_flip(flip, update_config),
+            Input::Test(input) => input.set_flip(flip, update_config),
+            Input::Fake(input) => input.set_flip(flip, update_config),
+            Input::Audio(input) => input.set_flip(flip, update_config),
+            Input::Playlist(input) => input.set_flip(flip, update_config),
+            Input::Replay(input) => input.set_flip(flip, update_config),
+        }
+    }
+
+    pub fn set_rotation(
+        &mut self,
+        rotation: Option<VideoRotation>,
+        update_config: bool,
+    ) -> Result<()> {
+        match self {
+            Input::URI(input) => input.set_rotation(rotation, update_config),
+            Input::Test(input) => input.set_rotation(rotation, update_config),
+            Input::Fake(input) => input.set_rotation(rotation, update_config),
+            Input::Audio(input) => input.set_rotation(rotation, update_config),
+            Input::Playlist(input) => input.set_rotation(rotation, update_config),
+            Input::Replay(input) => input.set_rotation(rotation, update_config),
+        }
+    }
+
+    pub fn set_scaling_mode(
+        &mut self,
+        scaling_mode: ScalingMode,
+        update_config: bool,
+    ) -> Result<()> {
+        match self {
+            Input::URI(input) => input.set_scaling_mode(scaling_mode, update_config),
+            Input::Test(input) => input.set_scaling_mode(scaling_mode, update_config),
+            Input::Fake(input) => input.set_scaling_mode(scaling_mode, update_config),
+            Input::Audio(input) => input.set_scaling_mode(scaling_mode, update_config),
+            Input::Playlist(input) => input.set_scaling_mode(scaling_mode, update_config),
+            Input::Replay(input) => input.set_scaling_mode(scaling_mode, update_config),
+        }
+    }
+
+    pub fn set_bar_color(&mut self, bar_color: BarColor, update_config: bool) -> Result<()> {
+        match self {
+            Input::URI(input) => input.set_bar_color(bar_color, update_config),
+            Input::Test(input) => input.set_bar_color(bar_color, update_config),
+            Input::Fake(input) => input.set_bar_color(bar_color, update_config),
+            Input::Audio(input) => input.set_bar_color(bar_color, update_config),
+            Input::Playlist(input) => input.set_bar_color(bar_color, update_config),
+            Input::Replay(input) => input.set_bar_color(bar_color, update_config),
+        }
+    }
+
+    pub fn set_color(&mut self, color: ColorConfig, update_config: bool) -> Result<()> {
+        match self {
+            Input::URI(input) => input.set_color(color, update_config),
+            Input::Test(input) => input.set_color(color, update_config),
+            Input::Fake(input) => input.set_color(color, update_config),
+            Input::Audio(input) => input.set_color(color, update_config),
+            Input::Playlist(input) => input.set_color(color, update_config),
+            Input::Replay(input) => input.set_color(color, update_config),
+        }
+    }
+
+    /// Returns the compositor pad the input's video is mixed through, if it has video and is
+    /// linked.
+    pub fn video_pad(&self) -> Option<gst::Pad> {
+        match self {
+            Input::URI(input) => input.video_pad(),
+            Input::Test(input) => input.video_pad(),
+            Input::Fake(input) => input.video_pad(),
+            Input::Audio(_) => None,
+            Input::Playlist(input) => input.video_pad(),
+            Input::Replay(input) => input.video_pad(),
+        }
+    }
+
+    /// Returns the tee carrying the processed video of the input. Audio inputs have no video and
+    /// fake inputs nothing worth showing, so neither has one.
+    pub fn video_tee(&self) -> Option<gst::Element> {
+        match self {
+            Input::URI(input) => Some(input.video_tee()),
+            Input::Test(input) => Some(input.video_tee()),
+            Input::Playlist(input) => Some(input.video_tee()),
+            Input::Replay(input) => Some(input.video_tee()),
+            Input::Fake(_) | Input::Audio(_) => None,
+        }
+    }
+
+    /// Returns the tee carrying the processed audio of the input, for inputs which have one.
+    pub fn audio_tee(&self) -> Option<gst::Element> {
+        match self {
+            Input::URI(input) => Some(input.audio_tee()),
+            _ => None,
+        }
+    }
+
+    /// Returns the audio mixer pad the input's audio is mixed through, if it is linked.
+    pub fn audio_pad(&self) -> Option<gst::Pad> {
+        match self {
+            Input::URI(input) => input.audio_pad(),
+            Input::Test(input) => input.audio_pad(),
+            Input::Fake(input) => input.audio_pad(),
+            Input::Audio(input) => input.audio_pad(),
+            Input::Playlist(input) => input.audio_pad(),
+            Input::Replay(input) => input.audio_pad(),
         }
     }
 
@@ -152,7 +457,240 @@ impl Input {
             Input::URI(input) => input.config(),
             Input::Test(input) => input.config(),
             Input::Fake(input) => input.config(),
+            Input::Audio(input) => input.config(),
+            Input::Playlist(input) => input.config(),
+            Input::Replay(input) => input.config(),
+        }
+    }
+}
+
+fn set_crop_properties(video_crop: &gst::Element, crop: &CropConfig) -> Result<()> {
+    video_crop.set_property("top", &crop.top)?;
+    video_crop.set_property("bottom", &crop.bottom)?;
+    video_crop.set_property("left", &crop.left)?;
+    video_crop.set_property("right", &crop.right)?;
+    Ok(())
+}
+
+fn set_color_properties(video_balance: &gst::Element, color: &ColorConfig) -> Result<()> {
+    video_balance.set_property("brightness", &color.brightness)?;
+    video_balance.set_property("contrast", &color.contrast)?;
+    video_balance.set_property("saturation", &color.saturation)?;
+    video_balance.set_property("hue", &color.hue)?;
+    Ok(())
+}
+
+/// Sets the `videoflip` method matching a flip followed by a clockwise rotation.
+fn set_flip_properties(
+    video_flip: &gst::Element,
+    flip: Option<&VideoFlip>,
+    rotation: Option<&VideoRotation>,
+) {
+    use VideoFlip::*;
+    use VideoRotation::*;
+
+    let method = match (flip, rotation) {
+        (None, None) => "none",
+        (None, Some(Rotate90)) => "clockwise",
+        (None, Some(Rotate180)) => "rotate-180",
+        (None, Some(Rotate270)) => "counterclockwise",
+        (Some(Horizontal), None) | (Some(Vertical), Some(Rotate180)) => "horizontal-flip",
+        (Some(Vertical), None) | (Some(Horizontal), Some(Rotate180)) => "vertical-flip",
+        (Some(Vertical), Some(Rotate90)) | (Some(Horizontal), Some(Rotate270)) => {
+            "upper-left-diagonal"
+        }
+        (Some(Horizontal), Some(Rotate90)) | (Some(Vertical), Some(Rotate270)) => {
+            "upper-right-diagonal"
+        }
+    };
+
+    video_flip.set_property_from_str("method", method);
+}
+
+/// Largest offset in milliseconds either branch of an input can be delayed by.
+pub const MAX_AV_OFFSET_MS: i64 = 10_000;
+
+/// Converts an audio/video offset in milliseconds to nanoseconds, rejecting offsets of more than
+/// [`MAX_AV_OFFSET_MS`](constant.MAX_AV_OFFSET_MS.html) in either direction.
+pub(crate) fn av_offset_ns(av_offset_ms: i64) -> Result<i64> {
+    if !(-MAX_AV_OFFSET_MS..=MAX_AV_OFFSET_MS).contains(&av_offset_ms) {
+        return Err(MixerError::InvalidConfig(format!(
+            "av offset must be within ±{}ms",
+            MAX_AV_OFFSET_MS
+        )));
+    }
+    av_offset_ms
+        .checked_mul(gst::MSECOND_VAL as i64)
+        .ok_or_else(|| MixerError::InvalidConfig("av offset is out of range".to_string()))
+}
+
+/// Shifts the audio relative to the video by offsetting the src pads of the first element in each
+/// branch. These offsets add up with the running time offset applied to the decoded pads, and can
+/// be changed at any time without relinking. Only one branch is ever delayed, so that timestamps
+/// are never moved into the past.
+fn set_av_offset_properties(
+    audio_convert: &gst::Element,
+    video_convert: &gst::Element,
+    av_offset_ms: i64,
+) -> Result<()> {
+    let offset = av_offset_ns(av_offset_ms)?;
+
+    audio_convert
+        .get_static_pad("src")
+        .ok_or_else(|| MixerError::Gstreamer("Failed to get src pad of audio".to_string()))?
+        .set_offset(offset.max(0));
+    video_convert
+        .get_static_pad("src")
+        .ok_or_else(|| MixerError::Gstreamer("Failed to get src pad of video".to_string()))?
+        .set_offset((-offset).max(0));
+
+    Ok(())
+}
+
+/// Rejects `value` for a setting the input has no elements for, unless it is left at `default`.
+fn unsupported<T: PartialEq>(setting: &str, value: &T, default: &T) -> Result<()> {
+    if value == default {
+        return Ok(());
+    }
+    Err(MixerError::InvalidConfig(format!(
+        "{} is not supported by this input",
+        setting
+    )))
+}
+
+/// Converts a gain in dB to the linear volume used by the `volume` element, which is capped at
+/// 10.0 (+20dB).
+fn gain_to_volume(gain: f64) -> f64 {
+    10f64.powf(gain / 20.0).min(10.0)
+}
+
+fn set_equalizer_properties(equalizer: &gst::Element, config: &EqualizerConfig) -> Result<()> {
+    equalizer.set_property("band0", &config.low)?;
+    equalizer.set_property("band1", &config.mid)?;
+    equalizer.set_property("band2", &config.high)?;
+    Ok(())
+}
+
+/// A ratio of 1.0 leaves the audio untouched, which is used while the compressor is disabled.
+fn set_compressor_properties(
+    compressor: &gst::Element,
+    config: Option<&CompressorConfig>,
+) -> Result<()> {
+    match config {
+        Some(config) => {
+            compressor.set_property("threshold", &config.threshold)?;
+            compressor.set_property("ratio", &config.ratio)?;
         }
+        None => compressor.set_property("ratio", &1.0f32)?,
+    }
+    Ok(())
+}
+
+/// A ratio of 1.0 leaves the audio untouched, which is used while the noise gate is disabled.
+fn set_noise_gate_properties(gate: &gst::Element, config: Option<&NoiseGateConfig>) -> Result<()> {
+    match config {
+        Some(config) => {
+            gate.set_property("threshold", &config.threshold)?;
+            gate.set_property("ratio", &config.ratio)?;
+        }
+        None => gate.set_property("ratio", &1.0f32)?,
+    }
+    Ok(())
+}
+
+/// Seeks a decoding source through its src pads, as bins only forward seeks to their sinks.
+/// A flushing seek restarts the running time of the source from zero, so its pads are offset by
+/// the current running time again to keep the source from fast-forwarding to catch up with the
+/// pipeline.
+fn seek_source(
+    source: &gst::Element,
+    position: gst::ClockTime,
+    stop: Option<gst::ClockTime>,
+) -> Result<()> {
+    let pads = source.get_src_pads();
+    let pad = pads
+        .first()
+        .ok_or_else(|| MixerError::Gstreamer("Source has no pads to seek".to_string()))?;
+
+    let running_time = source.get_current_running_time();
+    for pad in pads.iter() {
+        pad.set_offset(gst::format::GenericFormattedValue::Time(running_time).get_value());
+    }
+
+    let (stop_type, stop) = match stop {
+        Some(stop) => (gst::SeekType::Set, stop),
+        None => (gst::SeekType::None, gst::ClockTime::none()),
+    };
+    let seek = gst::event::Seek::new(
+        1.0,
+        gst::SeekFlags::FLUSH | gst::SeekFlags::ACCURATE,
+        gst::SeekType::Set,
+        position,
+        stop_type,
+        stop,
+    );
+    if !pad.send_event(seek) {
+        return Err(MixerError::Gstreamer("Seek was not handled".to_string()));
+    }
+
+    Ok(())
+}
+
+/// Copies the buffers flowing through `pad` into `appsrc` of another pipeline, timestamped with
+/// their running time in the mixer minus `offset` nanoseconds. Buffers from before the offset are
+/// skipped. Returns the probe to remove once done.
+pub(crate) fn tap_pad(
+    pad: &gst::Pad,
+    appsrc: &gst::Element,
+    offset: u64,
+) -> Result<gst::PadProbeId> {
+    let appsrc = appsrc.clone();
+    let caps: Mutex<Option<gst::Caps>> = Mutex::new(None);
+
+    pad.add_probe(gst::PadProbeType::BUFFER, move |pad, info| {
+        if let Some(gst::PadProbeData::Buffer(buffer)) = &info.data {
+            if let (Some(current), Ok(mut caps)) = (pad.get_current_caps(), caps.lock()) {
+                if caps.as_ref() != Some(&current) {
+                    let _ = appsrc.set_property("caps", &current);
+                    *caps = Some(current);
+                }
+            }
+
+            let running_time = match buffer_running_time(pad, buffer).nseconds() {
+                Some(running_time) if running_time >= offset => running_time - offset,
+                _ => return gst::PadProbeReturn::Ok,
+            };
+            let mut buffer = buffer.copy();
+            if let Some(buffer) = buffer.get_mut() {
+                buffer.set_pts(gst::ClockTime::from_nseconds(running_time));
+                buffer.set_dts(gst::ClockTime::none());
+            }
+            let _ = appsrc.emit("push-buffer", &[&buffer]);
+        }
+        gst::PadProbeReturn::Ok
+    })
+    .ok_or_else(|| MixerError::Gstreamer("failed to add probe".to_string()))
+}
+
+/// Running time of a buffer flowing through `pad`, from the segment of the pad or otherwise the
+/// clock of its element.
+fn buffer_running_time(pad: &gst::Pad, buffer: &gst::BufferRef) -> gst::ClockTime {
+    let running_time =
+        pad.get_sticky_event(gst::EventType::Segment, 0)
+            .and_then(|event| match event.view() {
+                gst::EventView::Segment(segment) => segment
+                    .get_segment()
+                    .downcast_ref::<gst::ClockTime>()
+                    .map(|segment| segment.to_running_time(buffer.get_pts())),
+                _ => None,
+            });
+
+    match running_time {
+        Some(running_time) if running_time.is_some() => running_time,
+        _ => pad
+            .get_parent_element()
+            .map(|element| element.get_current_running_time())
+            .unwrap_or_else(gst::ClockTime::none),
     }
 }
 
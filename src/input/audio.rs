@@ -0,0 +1,398 @@
+use super::Config;
+use crate::{
+    gst_create_element, mixer, AudioMode, BarColor, ChromaKeyConfig, ColorConfig, CompressorConfig,
+    CropConfig, EqualizerConfig, MixerAudioConfig, NoiseGateConfig, Result, ScalingMode, VideoFlip,
+    VideoRotation,
+};
+
+use gst::prelude::*;
+use gstreamer as gst;
+
+/// Audio-only input, such as an internet radio feed or a music bed. It has no compositor pad, so
+/// every video setting is ignored.
+pub struct Audio {
+    pub name: String,
+    pub location: String,
+    pub looping: bool,
+    config: Config,
+    pipeline: Option<gst::Pipeline>,
+    source: gst::Element,
+    audio_convert: gst::Element,
+    audio_gain: gst::Element,
+    audio_equalizer: gst::Element,
+    audio_gate: gst::Element,
+    audio_compressor: gst::Element,
+    audio_volume: gst::Element,
+    audio_resample: gst::Element,
+    audio_format_convert: gst::Element,
+    audio_capsfilter: gst::Element,
+    audio_queue: gst::Element,
+}
+
+impl Audio {
+    /// Creates an audio input from `uri`. When `looping` is set the input starts over from the
+    /// beginning whenever it reaches the end.
+    pub fn create(config: Config, uri: &str, looping: bool) -> Result<Self> {
+        let source = gst_create_element("uridecodebin", &format!("input_{}_source", config.name))?;
+        source.set_property("uri", &uri)?;
+        // Only expose the audio of sources which also carry video.
+        source.set_property("caps", &gst::Caps::new_simple("audio/x-raw", &[]))?;
+        source.set_property("expose-all-streams", &false)?;
+
+        let audio_convert = gst_create_element(
+            "audioconvert",
+            &format!("input_{}_audio_convert", config.name),
+        )?;
+        let audio_gain =
+            gst_create_element("volume", &format!("input_{}_audio_gain", config.name))?;
+        audio_gain.set_property("volume", &super::gain_to_volume(config.audio.gain))?;
+        let audio_equalizer = gst_create_element(
+            "equalizer-3bands",
+            &format!("input_{}_audio_equalizer", config.name),
+        )?;
+        super::set_equalizer_properties(&audio_equalizer, &config.audio.equalizer)?;
+        let audio_gate =
+            gst_create_element("audiodynamic", &format!("input_{}_audio_gate", config.name))?;
+        audio_gate.set_property_from_str("mode", "expander");
+        super::set_noise_gate_properties(&audio_gate, config.audio.noise_gate.as_ref())?;
+        let audio_compressor = gst_create_element(
+            "audiodynamic",
+            &format!("input_{}_audio_compressor", config.name),
+        )?;
+        audio_compressor.set_property_from_str("mode", "compressor");
+        audio_compressor.set_property_from_str("characteristics", "soft-knee");
+        super::set_compressor_properties(&audio_compressor, config.audio.compressor.as_ref())?;
+        let audio_volume =
+            gst_create_element("volume", &format!("input_{}_audio_volume", config.name))?;
+        audio_volume.set_property("volume", &config.audio.volume)?;
+        audio_volume.set_property("mute", &config.audio.mute)?;
+        let audio_resample = gst_create_element(
+            "audioresample",
+            &format!("input_{}_audio_resample", config.name),
+        )?;
+        // The dynamics and equalizer elements only handle a few sample formats, the audio is
+        // converted to the program format once it has been processed.
+        let audio_format_convert = gst_create_element(
+            "audioconvert",
+            &format!("input_{}_audio_format_convert", config.name),
+        )?;
+        let audio_capsfilter = gst_create_element(
+            "capsfilter",
+            &format!("input_{}_audio_capsfilter", config.name),
+        )?;
+        audio_capsfilter.set_property("caps", &MixerAudioConfig::default().caps())?;
+        let audio_queue =
+            gst_create_element("queue", &format!("input_{}_audio_queue", config.name))?;
+
+        set_av_offset_properties(&audio_convert, config.audio.av_offset_ms)?;
+
+        let audio = audio_convert.clone();
+        source.connect_pad_added(move |_, src_pad| {
+            let sink_pad = audio
+                .get_static_pad("sink")
+                .expect("Failed to get sink pad from audio convert");
+            if sink_pad.is_linked() {
+                return;
+            }
+
+            // Offset src_pad by current running time. So that the audio does not fast-forward
+            // to get in sync with running time of pipeline.
+            let running_time = audio.get_current_running_time();
+            src_pad.set_offset(gst::format::GenericFormattedValue::Time(running_time).get_value());
+
+            if let Err(e) = src_pad.link(&sink_pad) {
+                tracing::warn!("Failed to link audio input: {:?}", e);
+            }
+        });
+
+        if looping {
+            let weak_source = source.downgrade();
+            audio_convert
+                .get_static_pad("sink")
+                .ok_or_else(|| {
+                    mixer::Error::Gstreamer("Failed to get sink pad of audio".to_string())
+                })?
+                .add_probe(gst::PadProbeType::EVENT_DOWNSTREAM, move |_, info| {
+                    match &info.data {
+                        Some(gst::PadProbeData::Event(event))
+                            if event.get_type() == gst::EventType::Eos => {}
+                        _ => return gst::PadProbeReturn::Ok,
+                    }
+
+                    // Seeking from the streaming thread would deadlock, so restart elsewhere and
+                    // keep the EOS from reaching the mixer.
+                    if let Some(source) = weak_source.upgrade() {
+                        std::thread::spawn(move || {
+                            if let Err(e) =
+                                super::seek_source(&source, gst::ClockTime::from_seconds(0), None)
+                            {
+                                tracing::warn!("Failed to loop audio input: {}", e);
+                            }
+                        });
+                    }
+                    gst::PadProbeReturn::Drop
+                });
+        }
+
+        Ok(Self {
+            name: config.name.to_string(),
+            location: uri.to_string(),
+            looping,
+            config,
+            pipeline: None,
+            source,
+            audio_convert,
+            audio_gain,
+            audio_equalizer,
+            audio_gate,
+            audio_compressor,
+            audio_volume,
+            audio_resample,
+            audio_format_convert,
+            audio_capsfilter,
+            audio_queue,
+        })
+    }
+
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    pub fn link(
+        &mut self,
+        pipeline: gst::Pipeline,
+        audio: gst::Element,
+        _video: gst::Element,
+    ) -> Result<()> {
+        pipeline.add_many(&[
+            &self.source,
+            &self.audio_convert,
+            &self.audio_gain,
+            &self.audio_equalizer,
+            &self.audio_gate,
+            &self.audio_compressor,
+            &self.audio_volume,
+            &self.audio_resample,
+            &self.audio_format_convert,
+            &self.audio_capsfilter,
+            &self.audio_queue,
+        ])?;
+
+        self.pipeline = Some(pipeline);
+
+        gst::Element::link_many(&[
+            &self.audio_convert,
+            &self.audio_gain,
+            &self.audio_equalizer,
+            &self.audio_gate,
+            &self.audio_compressor,
+            &self.audio_volume,
+            &self.audio_resample,
+            &self.audio_format_convert,
+            &self.audio_capsfilter,
+            &self.audio_queue,
+            &audio,
+        ])?;
+
+        Ok(())
+    }
+
+    pub fn unlink(&self) -> Result<()> {
+        super::release_request_pad(&self.audio_queue)?;
+
+        if let Some(pipeline) = self.pipeline.as_ref() {
+            pipeline.remove_many(&[
+                &self.source,
+                &self.audio_convert,
+                &self.audio_gain,
+                &self.audio_equalizer,
+                &self.audio_gate,
+                &self.audio_compressor,
+                &self.audio_volume,
+                &self.audio_resample,
+                &self.audio_format_convert,
+                &self.audio_capsfilter,
+                &self.audio_queue,
+            ])?;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_state(&mut self, state: gst::State) -> Result<()> {
+        self.source.set_state(state)?;
+        self.audio_convert.set_state(state)?;
+        self.audio_gain.set_state(state)?;
+        self.audio_equalizer.set_state(state)?;
+        self.audio_gate.set_state(state)?;
+        self.audio_compressor.set_state(state)?;
+        self.audio_volume.set_state(state)?;
+        self.audio_resample.set_state(state)?;
+        self.audio_format_convert.set_state(state)?;
+        self.audio_capsfilter.set_state(state)?;
+        self.audio_queue.set_state(state)?;
+        Ok(())
+    }
+
+    pub fn set_volume(&mut self, volume: f64, update_config: bool) -> Result<()> {
+        if update_config {
+            self.config.audio.volume = volume;
+        }
+        self.audio_volume.set_property("volume", &volume)?;
+        Ok(())
+    }
+
+    pub fn set_mute(&mut self, mute: bool, update_config: bool) -> Result<()> {
+        if update_config {
+            self.config.audio.mute = mute;
+        }
+        self.audio_volume.set_property("mute", &mute)?;
+        Ok(())
+    }
+
+    pub fn set_gain(&mut self, gain: f64, update_config: bool) -> Result<()> {
+        if update_config {
+            self.config.audio.gain = gain;
+        }
+        self.audio_gain
+            .set_property("volume", &super::gain_to_volume(gain))?;
+        Ok(())
+    }
+
+    pub fn set_equalizer(&mut self, equalizer: EqualizerConfig, update_config: bool) -> Result<()> {
+        super::set_equalizer_properties(&self.audio_equalizer, &equalizer)?;
+        if update_config {
+            self.config.audio.equalizer = equalizer;
+        }
+        Ok(())
+    }
+
+    pub fn set_compressor(
+        &mut self,
+        compressor: Option<CompressorConfig>,
+        update_config: bool,
+    ) -> Result<()> {
+        super::set_compressor_properties(&self.audio_compressor, compressor.as_ref())?;
+        if update_config {
+            self.config.audio.compressor = compressor;
+        }
+        Ok(())
+    }
+
+    pub fn set_noise_gate(
+        &mut self,
+        noise_gate: Option<NoiseGateConfig>,
+        update_config: bool,
+    ) -> Result<()> {
+        super::set_noise_gate_properties(&self.audio_gate, noise_gate.as_ref())?;
+        if update_config {
+            self.config.audio.noise_gate = noise_gate;
+        }
+        Ok(())
+    }
+
+    pub fn set_av_offset(&mut self, av_offset_ms: i64, update_config: bool) -> Result<()> {
+        set_av_offset_properties(&self.audio_convert, av_offset_ms)?;
+        if update_config {
+            self.config.audio.av_offset_ms = av_offset_ms;
+        }
+        Ok(())
+    }
+
+    pub fn set_audio_mode(&mut self, mode: AudioMode, update_config: bool) -> Result<()> {
+        if update_config {
+            self.config.audio.mode = mode;
+        }
+        Ok(())
+    }
+
+    pub fn set_audio_format(&mut self, audio: &MixerAudioConfig) -> Result<()> {
+        self.audio_capsfilter.set_property("caps", &audio.caps())?;
+        Ok(())
+    }
+
+    pub fn set_zorder(&mut self, _zorder: u32, _update_config: bool) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_width(&mut self, _width: i32, _update_config: bool) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_height(&mut self, _height: i32, _update_config: bool) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_xpos(&mut self, _xpos: i32, _update_config: bool) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_ypos(&mut self, _ypos: i32, _update_config: bool) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_alpha(&mut self, _alpha: f64, _update_config: bool) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_chroma_key(
+        &mut self,
+        _chroma_key: Option<ChromaKeyConfig>,
+        _update_config: bool,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_crop(&mut self, _crop: CropConfig, _update_config: bool) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_flip(&mut self, _flip: Option<VideoFlip>, _update_config: bool) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_rotation(
+        &mut self,
+        _rotation: Option<VideoRotation>,
+        _update_config: bool,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_scaling_mode(
+        &mut self,
+        _scaling_mode: ScalingMode,
+        _update_config: bool,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_bar_color(&mut self, _bar_color: BarColor, _update_config: bool) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_color(&mut self, _color: ColorConfig, _update_config: bool) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn audio_pad(&self) -> Option<gst::Pad> {
+        self.audio_queue
+            .get_static_pad("src")
+            .and_then(|pad| pad.get_peer())
+    }
+
+    pub fn config(&self) -> Config {
+        self.config.clone()
+    }
+}
+
+/// Delays the audio by the offset. There is no video to delay instead, so negative offsets are
+/// ignored.
+fn set_av_offset_properties(audio_convert: &gst::Element, av_offset_ms: i64) -> Result<()> {
+    let offset = super::av_offset_ns(av_offset_ms)?;
+    audio_convert
+        .get_static_pad("src")
+        .ok_or_else(|| mixer::Error::Gstreamer("Failed to get src pad of audio".to_string()))?
+        .set_offset(offset.max(0));
+    Ok(())
+}
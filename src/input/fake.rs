@@ -1,5 +1,9 @@
 use super::Config;
-use crate::{gst_create_element, mixer::Error as MixerError, Result};
+use crate::{
+    gst_create_element, mixer::Error as MixerError, AudioMode, BarColor, ChromaKeyConfig,
+    ColorConfig, CompressorConfig, CropConfig, EqualizerConfig, MixerAudioConfig, NoiseGateConfig,
+    Result, ScalingMode, VideoFlip, VideoRotation,
+};
 
 use gst::prelude::*;
 use gstreamer as gst;
@@ -68,6 +72,53 @@ impl Fake {
         Ok(())
     }
 
+    pub fn set_mute(&mut self, _mute: bool, _update_config: bool) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_gain(&mut self, _gain: f64, _update_config: bool) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_equalizer(
+        &mut self,
+        _equalizer: EqualizerConfig,
+        _update_config: bool,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_compressor(
+        &mut self,
+        _compressor: Option<CompressorConfig>,
+        _update_config: bool,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_noise_gate(
+        &mut self,
+        _noise_gate: Option<NoiseGateConfig>,
+        _update_config: bool,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_av_offset(&mut self, _av_offset_ms: i64, _update_config: bool) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_audio_mode(&mut self, mode: AudioMode, update_config: bool) -> Result<()> {
+        if update_config {
+            self.config.audio.mode = mode;
+        }
+        Ok(())
+    }
+
+    pub fn set_audio_format(&mut self, _audio: &MixerAudioConfig) -> Result<()> {
+        Ok(())
+    }
+
     pub fn set_zorder(&mut self, zorder: u32, update_config: bool) -> Result<()> {
         if update_config {
             self.config.video.zorder = Some(zorder);
@@ -104,6 +155,62 @@ impl Fake {
         Ok(())
     }
 
+    /// Fake inputs have no `alpha` element, the chroma key is only kept in the config.
+    pub fn set_chroma_key(
+        &mut self,
+        chroma_key: Option<ChromaKeyConfig>,
+        update_config: bool,
+    ) -> Result<()> {
+        if update_config {
+            self.config.video.chroma_key = chroma_key;
+        }
+        Ok(())
+    }
+
+    pub fn set_crop(&mut self, _crop: CropConfig, _update_config: bool) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_flip(&mut self, _flip: Option<VideoFlip>, _update_config: bool) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_rotation(
+        &mut self,
+        _rotation: Option<VideoRotation>,
+        _update_config: bool,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_scaling_mode(
+        &mut self,
+        _scaling_mode: ScalingMode,
+        _update_config: bool,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_bar_color(&mut self, _bar_color: BarColor, _update_config: bool) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_color(&mut self, _color: ColorConfig, _update_config: bool) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn video_pad(&self) -> Option<gst::Pad> {
+        self.video
+            .get_static_pad("src")
+            .and_then(|pad| pad.get_peer())
+    }
+
+    pub fn audio_pad(&self) -> Option<gst::Pad> {
+        self.audio
+            .get_static_pad("src")
+            .and_then(|pad| pad.get_peer())
+    }
+
     pub fn config(&self) -> Config {
         self.config.clone()
     }
@@ -0,0 +1,981 @@
+use super::Config;
+use crate::{
+    gst_create_element, mixer, secret::SecretUrl, AudioMode, BarColor, ChromaKeyConfig,
+    ColorConfig, CompressorConfig, CropConfig, EqualizerConfig, MixerAudioConfig, NoiseGateConfig,
+    Result, ScalingMode, VideoConfig, VideoFlip, VideoRotation,
+};
+
+use gst::prelude::*;
+use gstreamer as gst;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// Items decoded at a time, the current one and the next.
+const DECODED_ITEMS: usize = 2;
+
+/// Progress of a [`Playlist`](struct.Playlist.html)
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PlaylistStatus {
+    /// URI of the item currently playing.
+    pub current: Option<SecretUrl>,
+    pub position_ms: Option<u64>,
+    pub duration_ms: Option<u64>,
+    /// Time left of the current item.
+    pub remaining_ms: Option<u64>,
+    /// Time left of the current item and every queued item with a known duration.
+    pub playlist_remaining_ms: Option<u64>,
+    /// URIs of the items queued after the current item.
+    pub upcoming: Vec<SecretUrl>,
+    /// Set once the last item has ended, this never happens while looping.
+    pub finished: bool,
+}
+
+/// A file of the playlist, decoded into its own pads of the concat elements.
+struct Item {
+    uri: String,
+    /// Bin holding the `uridecodebin`, exposing the decoded streams on its `audio` and `video`
+    /// ghost pads.
+    source: gst::Element,
+    audio_pad: gst::Pad,
+    video_pad: gst::Pad,
+}
+
+/// Items waiting to be played, shared with the streaming threads which advance the playlist.
+struct Queue {
+    name: String,
+    uris: Vec<String>,
+    shuffle: bool,
+    looping: bool,
+    pipeline: Option<gst::Pipeline>,
+    /// Decoded items, the current one first.
+    items: Vec<Item>,
+    /// URIs to play after the decoded items, in order.
+    pending: VecDeque<String>,
+    sequence: u32,
+}
+
+/// Plays an ordered list of files gaplessly through `concat`. The next item is decoded ahead of
+/// time, so it starts on the very next buffer once the current item ends. Later items are only
+/// decoded once they are next.
+pub struct Playlist {
+    pub name: String,
+    config: Config,
+    pipeline: Option<gst::Pipeline>,
+    queue: Arc<Mutex<Queue>>,
+    offset_applied: Arc<AtomicBool>,
+    finished: Arc<AtomicBool>,
+    audio_concat: gst::Element,
+    audio_convert: gst::Element,
+    audio_gain: gst::Element,
+    audio_equalizer: gst::Element,
+    audio_gate: gst::Element,
+    audio_compressor: gst::Element,
+    audio_volume: gst::Element,
+    audio_resample: gst::Element,
+    audio_format_convert: gst::Element,
+    audio_capsfilter: gst::Element,
+    audio_queue: gst::Element,
+    video_concat: gst::Element,
+    video_convert: gst::Element,
+    video_crop: gst::Element,
+    video_flip: gst::Element,
+    video_balance: gst::Element,
+    video_scale: gst::Element,
+    video_rate: gst::Element,
+    video_capsfilter: gst::Element,
+    video_tee: gst::Element,
+    video_queue: gst::Element,
+}
+
+impl Playlist {
+    pub fn create(config: Config, uris: Vec<String>, shuffle: bool, looping: bool) -> Result<Self> {
+        if uris.is_empty() {
+            return Err(mixer::Error::InvalidConfig(
+                "a playlist needs at least one item".to_string(),
+            ));
+        }
+
+        let audio_concat =
+            gst_create_element("concat", &format!("input_{}_audio_concat", config.name))?;
+        let audio_convert = gst_create_element(
+            "audioconvert",
+            &format!("input_{}_audio_convert", config.name),
+        )?;
+        let audio_gain =
+            gst_create_element("volume", &format!("input_{}_audio_gain", config.name))?;
+        audio_gain.set_property("volume", &super::gain_to_volume(config.audio.gain))?;
+        let audio_equalizer = gst_create_element(
+            "equalizer-3bands",
+            &format!("input_{}_audio_equalizer", config.name),
+        )?;
+        super::set_equalizer_properties(&audio_equalizer, &config.audio.equalizer)?;
+        let audio_gate =
+            gst_create_element("audiodynamic", &format!("input_{}_audio_gate", config.name))?;
+        audio_gate.set_property_from_str("mode", "expander");
+        super::set_noise_gate_properties(&audio_gate, config.audio.noise_gate.as_ref())?;
+        let audio_compressor = gst_create_element(
+            "audiodynamic",
+            &format!("input_{}_audio_compressor", config.name),
+        )?;
+        audio_compressor.set_property_from_str("mode", "compressor");
+        audio_compressor.set_property_from_str("characteristics", "soft-knee");
+        super::set_compressor_properties(&audio_compressor, config.audio.compressor.as_ref())?;
+        let audio_volume =
+            gst_create_element("volume", &format!("input_{}_audio_volume", config.name))?;
+        audio_volume.set_property("volume", &config.audio.volume)?;
+        audio_volume.set_property("mute", &config.audio.mute)?;
+        let audio_resample = gst_create_element(
+            "audioresample",
+            &format!("input_{}_audio_resample", config.name),
+        )?;
+        // The dynamics and equalizer elements only handle a few sample formats, the audio is
+        // converted to the program format once it has been processed.
+        let audio_format_convert = gst_create_element(
+            "audioconvert",
+            &format!("input_{}_audio_format_convert", config.name),
+        )?;
+        let audio_capsfilter = gst_create_element(
+            "capsfilter",
+            &format!("input_{}_audio_capsfilter", config.name),
+        )?;
+        audio_capsfilter.set_property("caps", &MixerAudioConfig::default().caps())?;
+        let audio_queue =
+            gst_create_element("queue", &format!("input_{}_audio_queue", config.name))?;
+
+        let video_concat =
+            gst_create_element("concat", &format!("input_{}_video_concat", config.name))?;
+        let video_convert = gst_create_element(
+            "videoconvert",
+            &format!("input_{}_video_convert", config.name),
+        )?;
+        let video_crop =
+            gst_create_element("videocrop", &format!("input_{}_video_crop", config.name))?;
+        super::set_crop_properties(&video_crop, &config.video.crop)?;
+        let video_flip =
+            gst_create_element("videoflip", &format!("input_{}_video_flip", config.name))?;
+        super::set_flip_properties(
+            &video_flip,
+            config.video.flip.as_ref(),
+            config.video.rotation.as_ref(),
+        );
+        let video_balance = gst_create_element(
+            "videobalance",
+            &format!("input_{}_video_balance", config.name),
+        )?;
+        super::set_color_properties(&video_balance, &config.video.color)?;
+        let video_scale =
+            gst_create_element("videoscale", &format!("input_{}_video_scale", config.name))?;
+        let video_rate =
+            gst_create_element("videorate", &format!("input_{}_video_rate", config.name))?;
+        let video_capsfilter = gst_create_element(
+            "capsfilter",
+            &format!("input_{}_video_capsfilter", config.name),
+        )?;
+        let video_caps = gst::Caps::builder("video/x-raw")
+            .field("framerate", &gst::Fraction::new(config.video.framerate, 1))
+            .field("format", &config.video.format.to_string())
+            .field("width", &config.video.width)
+            .field("height", &config.video.height)
+            .field("pixel-aspect-ratio", &gst::Fraction::new(1, 1))
+            .build();
+        video_capsfilter.set_property("caps", &video_caps)?;
+        let video_tee = gst_create_element("tee", &format!("input_{}_video_tee", config.name))?;
+        video_tee.set_property("allow-not-linked", &true)?;
+        let video_queue =
+            gst_create_element("queue", &format!("input_{}_video_queue", config.name))?;
+
+        super::set_av_offset_properties(&audio_convert, &video_convert, config.audio.av_offset_ms)?;
+
+        let mut queue = Queue {
+            name: config.name.clone(),
+            uris,
+            shuffle,
+            looping,
+            pipeline: None,
+            items: vec![],
+            pending: VecDeque::new(),
+            sequence: 0,
+        };
+        queue.pending = queue.cycle().into();
+        let queue = Arc::new(Mutex::new(queue));
+        let offset_applied = Arc::new(AtomicBool::new(false));
+        let finished = Arc::new(AtomicBool::new(false));
+
+        // The video concat decides which item is current, the audio follows it.
+        let weak_audio_concat = audio_concat.downgrade();
+        let notify_queue = queue.clone();
+        let notify_offset_applied = offset_applied.clone();
+        video_concat.connect_notify(Some("active-pad"), move |video_concat, _| {
+            let active = match video_concat
+                .get_property("active-pad")
+                .ok()
+                .and_then(|value| value.get::<gst::Pad>().ok().flatten())
+            {
+                Some(pad) => pad,
+                None => return,
+            };
+            let audio_concat = match weak_audio_concat.upgrade() {
+                Some(concat) => concat,
+                None => return,
+            };
+
+            // Elements can't be added or removed from the streaming thread.
+            let video_concat = video_concat.clone();
+            let queue = notify_queue.clone();
+            let offset_applied = notify_offset_applied.clone();
+            std::thread::spawn(move || {
+                let mut queue = match queue.lock() {
+                    Ok(queue) => queue,
+                    Err(_) => return,
+                };
+                if let Err(e) =
+                    queue.advance(&active, &audio_concat, &video_concat, &offset_applied)
+                {
+                    tracing::warn!(input = queue.name.as_str(), "Failed to advance: {}", e);
+                }
+            });
+        });
+
+        // The queue may be locked by a thread waiting on this streaming thread, so only atomics
+        // are used here.
+        let eos_finished = finished.clone();
+        let eos_name = config.name.clone();
+        video_concat
+            .get_static_pad("src")
+            .ok_or_else(|| mixer::Error::Gstreamer("Failed to get src pad of concat".to_string()))?
+            .add_probe(gst::PadProbeType::EVENT_DOWNSTREAM, move |pad, info| {
+                match &info.data {
+                    Some(gst::PadProbeData::Event(event))
+                        if event.get_type() == gst::EventType::Eos => {}
+                    _ => return gst::PadProbeReturn::Ok,
+                }
+
+                eos_finished.store(true, Ordering::SeqCst);
+
+                if let Some(concat) = pad.get_parent_element() {
+                    let structure = gst::Structure::builder("playlist-finished")
+                        .field("input", &eos_name)
+                        .build();
+                    let message = gst::message::Application::builder(structure)
+                        .src(&concat)
+                        .build();
+                    let _ = concat.post_message(message);
+                }
+                gst::PadProbeReturn::Ok
+            });
+
+        Ok(Self {
+            name: config.name.to_string(),
+            config,
+            pipeline: None,
+            queue,
+            offset_applied,
+            finished,
+            audio_concat,
+            audio_convert,
+            audio_gain,
+            audio_equalizer,
+            audio_gate,
+            audio_compressor,
+            audio_volume,
+            audio_resample,
+            audio_format_convert,
+            audio_capsfilter,
+            audio_queue,
+            video_concat,
+            video_convert,
+            video_crop,
+            video_flip,
+            video_balance,
+            video_scale,
+            video_rate,
+            video_capsfilter,
+            video_tee,
+            video_queue,
+        })
+    }
+
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// Returns the URIs of the playlist in their configured order.
+    pub fn items(&self) -> Vec<String> {
+        self.queue
+            .lock()
+            .map(|queue| queue.uris.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn link(
+        &mut self,
+        pipeline: gst::Pipeline,
+        audio: gst::Element,
+        video: gst::Element,
+    ) -> Result<()> {
+        pipeline.add_many(&[
+            &self.audio_concat,
+            &self.audio_convert,
+            &self.audio_gain,
+            &self.audio_equalizer,
+            &self.audio_gate,
+            &self.audio_compressor,
+            &self.audio_volume,
+            &self.audio_resample,
+            &self.audio_format_convert,
+            &self.audio_capsfilter,
+            &self.audio_queue,
+            &self.video_concat,
+            &self.video_convert,
+            &self.video_crop,
+            &self.video_flip,
+            &self.video_balance,
+            &self.video_scale,
+            &self.video_rate,
+            &self.video_capsfilter,
+            &self.video_tee,
+            &self.video_queue,
+        ])?;
+
+        self.pipeline = Some(pipeline.clone());
+
+        gst::Element::link_many(&[
+            &self.audio_concat,
+            &self.audio_convert,
+            &self.audio_gain,
+            &self.audio_equalizer,
+            &self.audio_gate,
+            &self.audio_compressor,
+            &self.audio_volume,
+            &self.audio_resample,
+            &self.audio_format_convert,
+            &self.audio_capsfilter,
+            &self.audio_queue,
+            &audio,
+        ])?;
+        gst::Element::link_many(&[
+            &self.video_concat,
+            &self.video_convert,
+            &self.video_crop,
+            &self.video_flip,
+            &self.video_balance,
+            &self.video_scale,
+            &self.video_rate,
+            &self.video_capsfilter,
+            &self.video_tee,
+            &self.video_queue,
+            &video,
+        ])?;
+
+        let compositor_pad = self
+            .video_queue
+            .get_static_pad("src")
+            .ok_or_else(|| mixer::Error::Gstreamer("Failed to get src pad of video".to_string()))?
+            .get_peer()
+            .ok_or_else(|| {
+                mixer::Error::Gstreamer("Failed to retrieve peer pad for video_queue".to_string())
+            })?;
+        if let Some(zorder) = self.config.video.zorder {
+            compositor_pad.set_property("zorder", &zorder)?;
+        }
+        compositor_pad.set_property("alpha", &self.config.video.alpha)?;
+        compositor_pad.set_property("xpos", &self.config.video.xpos)?;
+        compositor_pad.set_property("ypos", &self.config.video.ypos)?;
+
+        // Store the zorder picked by the compositor, see `URI::link`.
+        let zorder = compositor_pad
+            .get_property("zorder")?
+            .get_some::<u32>()
+            .map_err(|_| mixer::Error::Unknown)?;
+        self.config.video.zorder = Some(zorder);
+
+        let mut queue = self.queue.lock().map_err(|_| mixer::Error::Unknown)?;
+        queue.pipeline = Some(pipeline);
+        queue.fill(&self.audio_concat, &self.video_concat, &self.offset_applied)
+    }
+
+    pub fn unlink(&self) -> Result<()> {
+        super::release_request_pad(&self.audio_queue)?;
+        super::release_request_pad(&self.video_queue)?;
+
+        let mut queue = self.queue.lock().map_err(|_| mixer::Error::Unknown)?;
+        for item in queue.items.drain(..).collect::<Vec<Item>>() {
+            queue.remove_item(item)?;
+        }
+
+        if let Some(pipeline) = self.pipeline.as_ref() {
+            pipeline.remove_many(&[
+                &self.audio_concat,
+                &self.audio_convert,
+                &self.audio_gain,
+                &self.audio_equalizer,
+                &self.audio_gate,
+                &self.audio_compressor,
+                &self.audio_volume,
+                &self.audio_resample,
+                &self.audio_format_convert,
+                &self.audio_capsfilter,
+                &self.audio_queue,
+                &self.video_concat,
+                &self.video_convert,
+                &self.video_crop,
+                &self.video_flip,
+                &self.video_balance,
+                &self.video_scale,
+                &self.video_rate,
+                &self.video_capsfilter,
+                &self.video_tee,
+                &self.video_queue,
+            ])?;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_state(&mut self, state: gst::State) -> Result<()> {
+        if let Ok(queue) = self.queue.lock() {
+            for item in queue.items.iter() {
+                item.source.set_state(state)?;
+            }
+        }
+        self.audio_concat.set_state(state)?;
+        self.audio_convert.set_state(state)?;
+        self.audio_gain.set_state(state)?;
+        self.audio_equalizer.set_state(state)?;
+        self.audio_gate.set_state(state)?;
+        self.audio_compressor.set_state(state)?;
+        self.audio_volume.set_state(state)?;
+        self.audio_resample.set_state(state)?;
+        self.audio_format_convert.set_state(state)?;
+        self.audio_capsfilter.set_state(state)?;
+        self.audio_queue.set_state(state)?;
+        self.video_concat.set_state(state)?;
+        self.video_convert.set_state(state)?;
+        self.video_crop.set_state(state)?;
+        self.video_flip.set_state(state)?;
+        self.video_balance.set_state(state)?;
+        self.video_scale.set_state(state)?;
+        self.video_rate.set_state(state)?;
+        self.video_capsfilter.set_state(state)?;
+        self.video_tee.set_state(state)?;
+        self.video_queue.set_state(state)?;
+        Ok(())
+    }
+
+    /// Skips to the next item by ending the current one early.
+    pub fn next(&self) -> Result<()> {
+        let (audio_pad, video_pad) = {
+            let queue = self.queue.lock().map_err(|_| mixer::Error::Unknown)?;
+            let item = queue.items.first().ok_or_else(|| {
+                mixer::Error::InvalidConfig("the playlist has finished".to_string())
+            })?;
+            (item.audio_pad.clone(), item.video_pad.clone())
+        };
+
+        // The queue must not be locked here, the EOS switches items from this thread.
+        audio_pad.send_event(gst::event::Eos::new());
+        video_pad.send_event(gst::event::Eos::new());
+        Ok(())
+    }
+
+    pub fn status(&self) -> Result<PlaylistStatus> {
+        let queue = self.queue.lock().map_err(|_| mixer::Error::Unknown)?;
+        let current = queue.items.first();
+
+        let position_ms = current
+            .and_then(|item| item.video_pad.peer_query_position::<gst::ClockTime>())
+            .and_then(|position| position.mseconds());
+        let duration_ms = current.and_then(Item::duration_ms);
+        let remaining_ms = match (position_ms, duration_ms) {
+            (Some(position), Some(duration)) => Some(duration.saturating_sub(position)),
+            _ => None,
+        };
+        let playlist_remaining_ms = remaining_ms.map(|remaining| {
+            remaining
+                + queue
+                    .items
+                    .iter()
+                    .skip(1)
+                    .filter_map(Item::duration_ms)
+                    .sum::<u64>()
+        });
+
+        Ok(PlaylistStatus {
+            current: current.map(|item| SecretUrl::new(item.uri.as_str())),
+            position_ms,
+            duration_ms,
+            remaining_ms,
+            playlist_remaining_ms,
+            upcoming: queue
+                .items
+                .iter()
+                .skip(1)
+                .map(|item| item.uri.as_str())
+                .chain(queue.pending.iter().map(String::as_str))
+                .map(SecretUrl::new)
+                .collect(),
+            finished: self.finished.load(Ordering::SeqCst),
+        })
+    }
+
+    pub fn set_volume(&mut self, volume: f64, update_config: bool) -> Result<()> {
+        if update_config {
+            self.config.audio.volume = volume;
+        }
+        self.audio_volume.set_property("volume", &volume)?;
+        Ok(())
+    }
+
+    pub fn set_mute(&mut self, mute: bool, update_config: bool) -> Result<()> {
+        if update_config {
+            self.config.audio.mute = mute;
+        }
+        self.audio_volume.set_property("mute", &mute)?;
+        Ok(())
+    }
+
+    pub fn set_gain(&mut self, gain: f64, update_config: bool) -> Result<()> {
+        if update_config {
+            self.config.audio.gain = gain;
+        }
+        self.audio_gain
+            .set_property("volume", &super::gain_to_volume(gain))?;
+        Ok(())
+    }
+
+    pub fn set_equalizer(&mut self, equalizer: EqualizerConfig, update_config: bool) -> Result<()> {
+        super::set_equalizer_properties(&self.audio_equalizer, &equalizer)?;
+        if update_config {
+            self.config.audio.equalizer = equalizer;
+        }
+        Ok(())
+    }
+
+    pub fn set_compressor(
+        &mut self,
+        compressor: Option<CompressorConfig>,
+        update_config: bool,
+    ) -> Result<()> {
+        super::set_compressor_properties(&self.audio_compressor, compressor.as_ref())?;
+        if update_config {
+            self.config.audio.compressor = compressor;
+        }
+        Ok(())
+    }
+
+    pub fn set_noise_gate(
+        &mut self,
+        noise_gate: Option<NoiseGateConfig>,
+        update_config: bool,
+    ) -> Result<()> {
+        super::set_noise_gate_properties(&self.audio_gate, noise_gate.as_ref())?;
+        if update_config {
+            self.config.audio.noise_gate = noise_gate;
+        }
+        Ok(())
+    }
+
+    pub fn set_av_offset(&mut self, av_offset_ms: i64, update_config: bool) -> Result<()> {
+        super::set_av_offset_properties(&self.audio_convert, &self.video_convert, av_offset_ms)?;
+        if update_config {
+            self.config.audio.av_offset_ms = av_offset_ms;
+        }
+        Ok(())
+    }
+
+    pub fn set_audio_mode(&mut self, mode: AudioMode, update_config: bool) -> Result<()> {
+        if update_config {
+            self.config.audio.mode = mode;
+        }
+        Ok(())
+    }
+
+    pub fn set_audio_format(&mut self, audio: &MixerAudioConfig) -> Result<()> {
+        self.audio_capsfilter.set_property("caps", &audio.caps())?;
+        Ok(())
+    }
+
+    pub fn set_zorder(&mut self, zorder: u32, update_config: bool) -> Result<()> {
+        if update_config {
+            self.config.video.zorder = Some(zorder);
+        }
+        self.set_compositor_property("zorder", &zorder)
+    }
+
+    pub fn set_width(&mut self, width: i32, update_config: bool) -> Result<()> {
+        if update_config {
+            self.config.video.width = width;
+        }
+        self.set_compositor_property("width", &width)
+    }
+
+    pub fn set_height(&mut self, height: i32, update_config: bool) -> Result<()> {
+        if update_config {
+            self.config.video.height = height;
+        }
+        self.set_compositor_property("height", &height)
+    }
+
+    pub fn set_xpos(&mut self, xpos: i32, update_config: bool) -> Result<()> {
+        if update_config {
+            self.config.video.xpos = xpos;
+        }
+        self.set_compositor_property("xpos", &xpos)
+    }
+
+    pub fn set_ypos(&mut self, ypos: i32, update_config: bool) -> Result<()> {
+        if update_config {
+            self.config.video.ypos = ypos;
+        }
+        self.set_compositor_property("ypos", &ypos)
+    }
+
+    pub fn set_alpha(&mut self, alpha: f64, update_config: bool) -> Result<()> {
+        if update_config {
+            self.config.video.alpha = alpha;
+        }
+        self.set_compositor_property("alpha", &alpha)
+    }
+
+    /// Items are scaled to the size of the input, so there is no chroma key, scaling mode or bar
+    /// color to apply.
+    pub fn set_chroma_key(
+        &mut self,
+        chroma_key: Option<ChromaKeyConfig>,
+        _update_config: bool,
+    ) -> Result<()> {
+        super::unsupported("chroma key", &chroma_key, &None)
+    }
+
+    pub fn set_crop(&mut self, crop: CropConfig, update_config: bool) -> Result<()> {
+        super::set_crop_properties(&self.video_crop, &crop)?;
+        if update_config {
+            self.config.video.crop = crop;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_flip(&mut self, flip: Option<VideoFlip>, update_config: bool) -> Result<()> {
+        super::set_flip_properties(
+            &self.video_flip,
+            flip.as_ref(),
+            self.config.video.rotation.as_ref(),
+        );
+        if update_config {
+            self.config.video.flip = flip;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_rotation(
+        &mut self,
+        rotation: Option<VideoRotation>,
+        update_config: bool,
+    ) -> Result<()> {
+        super::set_flip_properties(
+            &self.video_flip,
+            self.config.video.flip.as_ref(),
+            rotation.as_ref(),
+        );
+        if update_config {
+            self.config.video.rotation = rotation;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_scaling_mode(
+        &mut self,
+        scaling_mode: ScalingMode,
+        _update_config: bool,
+    ) -> Result<()> {
+        super::unsupported(
+            "scaling mode",
+            &scaling_mode,
+            &VideoConfig::default().scaling_mode,
+        )
+    }
+
+    pub fn set_bar_color(&mut self, bar_color: BarColor, _update_config: bool) -> Result<()> {
+        super::unsupported("bar color", &bar_color, &VideoConfig::default().bar_color)
+    }
+
+    pub fn set_color(&mut self, color: ColorConfig, update_config: bool) -> Result<()> {
+        super::set_color_properties(&self.video_balance, &color)?;
+        if update_config {
+            self.config.video.color = color;
+        }
+
+        Ok(())
+    }
+
+    pub fn video_pad(&self) -> Option<gst::Pad> {
+        self.video_queue
+            .get_static_pad("src")
+            .and_then(|pad| pad.get_peer())
+    }
+
+    pub fn video_tee(&self) -> gst::Element {
+        self.video_tee.clone()
+    }
+
+    pub fn audio_pad(&self) -> Option<gst::Pad> {
+        self.audio_queue
+            .get_static_pad("src")
+            .and_then(|pad| pad.get_peer())
+    }
+
+    pub fn config(&self) -> Config {
+        self.config.clone()
+    }
+
+    fn set_compositor_property(&self, property: &str, value: &dyn ToValue) -> Result<()> {
+        super::set_peer_pad_property(
+            &self
+                .video_queue
+                .get_static_pad("src")
+                .ok_or_else(|| mixer::Error::Gstreamer("failed to retrieve src pad".to_string()))?,
+            property,
+            value,
+        )
+    }
+}
+
+impl Item {
+    fn duration_ms(&self) -> Option<u64> {
+        self.video_pad
+            .peer_query_duration::<gst::ClockTime>()
+            .and_then(|duration| duration.mseconds())
+    }
+}
+
+impl Queue {
+    /// Returns every URI once, shuffled when configured.
+    fn cycle(&self) -> Vec<String> {
+        let mut uris = self.uris.clone();
+        if self.shuffle {
+            shuffle(&mut uris);
+        }
+        uris
+    }
+
+    /// Decodes pending URIs until the current and the next item are ready, queueing another cycle
+    /// when looping and every URI has been decoded.
+    fn fill(
+        &mut self,
+        audio_concat: &gst::Element,
+        video_concat: &gst::Element,
+        offset_applied: &Arc<AtomicBool>,
+    ) -> Result<()> {
+        while self.items.len() < DECODED_ITEMS {
+            if self.pending.is_empty() && self.looping {
+                let cycle = self.cycle();
+                self.pending.extend(cycle);
+            }
+            let uri = match self.pending.pop_front() {
+                Some(uri) => uri,
+                None => break,
+            };
+            self.decode(uri, audio_concat, video_concat, offset_applied)?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds a source decoding `uri` into new pads of the concat elements, after the decoded items.
+    fn decode(
+        &mut self,
+        uri: String,
+        audio_concat: &gst::Element,
+        video_concat: &gst::Element,
+        offset_applied: &Arc<AtomicBool>,
+    ) -> Result<()> {
+        let pipeline = self
+            .pipeline
+            .clone()
+            .ok_or_else(|| mixer::Error::Gstreamer("Playlist is not linked".to_string()))?;
+
+        // The streams are exposed through ghost pads linked up front, which lets a failed item
+        // be ended from outside, see `end_failed_item`.
+        let bin = gst::Bin::new(Some(&format!("input_{}_item_{}", self.name, self.sequence)));
+        let decode = gst_create_element(
+            "uridecodebin",
+            &format!("input_{}_item_{}_decode", self.name, self.sequence),
+        )?;
+        decode.set_property("uri", &uri)?;
+        bin.add(&decode)?;
+        let audio_ghost = gst::GhostPad::new(Some("audio"), gst::PadDirection::Src);
+        let video_ghost = gst::GhostPad::new(Some("video"), gst::PadDirection::Src);
+        bin.add_pad(&audio_ghost)?;
+        bin.add_pad(&video_ghost)?;
+        let source = bin.upcast::<gst::Element>();
+        self.sequence += 1;
+
+        // Request the concat pads up front, they are played in the order they were requested.
+        let audio_pad = audio_concat.get_request_pad("sink_%u").ok_or_else(|| {
+            mixer::Error::Gstreamer("Failed to request audio concat pad".to_string())
+        })?;
+        let video_pad = video_concat.get_request_pad("sink_%u").ok_or_else(|| {
+            mixer::Error::Gstreamer("Failed to request video concat pad".to_string())
+        })?;
+
+        let ghost_pads = (audio_ghost.clone(), video_ghost.clone());
+        let src_pads = (
+            audio_concat.get_static_pad("src"),
+            video_concat.get_static_pad("src"),
+        );
+        let offset_applied = offset_applied.clone();
+        decode.connect_pad_added(move |src, src_pad| {
+            let caps = match src_pad.get_current_caps() {
+                Some(caps) => caps,
+                None => return,
+            };
+            let pad_type = match caps.get_structure(0) {
+                Some(structure) => structure.get_name().to_string(),
+                None => return,
+            };
+
+            // Offset the playlist by the running time at which it started, so that it does
+            // not fast-forward to get in sync with the running time of the pipeline.
+            if !offset_applied.swap(true, Ordering::SeqCst) {
+                let running_time =
+                    gst::format::GenericFormattedValue::Time(src.get_current_running_time())
+                        .get_value();
+                for pad in [&src_pads.0, &src_pads.1].iter().copied().flatten() {
+                    pad.set_offset(running_time);
+                }
+            }
+
+            let ghost_pad = if pad_type.starts_with("audio/x-raw") {
+                &ghost_pads.0
+            } else if pad_type.starts_with("video/x-raw") {
+                &ghost_pads.1
+            } else {
+                return;
+            };
+            if ghost_pad.get_target().is_none() {
+                if let Err(e) = ghost_pad.set_target(Some(src_pad)) {
+                    tracing::warn!("Failed to link playlist item: {:?}", e);
+                }
+            }
+        });
+
+        // Items without audio or video would stall the concat they never feed.
+        let unused_pads = (audio_ghost.clone(), video_ghost.clone());
+        decode.connect_no_more_pads(move |_| {
+            for pad in [&unused_pads.0, &unused_pads.1].iter() {
+                if pad.get_target().is_none() {
+                    pad.push_event(gst::event::Eos::new());
+                }
+            }
+        });
+
+        pipeline.add(&source)?;
+        for (ghost_pad, sink_pad) in [(&audio_ghost, &audio_pad), (&video_ghost, &video_pad)].iter()
+        {
+            ghost_pad
+                .link(*sink_pad)
+                .map_err(|e| mixer::Error::Gstreamer(e.to_string()))?;
+        }
+        // Items which fail to start post an error, which ends them.
+        if let Err(e) = source.sync_state_with_parent() {
+            tracing::warn!(
+                input = self.name.as_str(),
+                "Failed to start item {}: {}",
+                uri,
+                e
+            );
+        }
+
+        self.items.push(Item {
+            uri,
+            source,
+            audio_pad,
+            video_pad,
+        });
+
+        Ok(())
+    }
+
+    /// Drops the items played before `active` and decodes the item following it.
+    fn advance(
+        &mut self,
+        active: &gst::Pad,
+        audio_concat: &gst::Element,
+        video_concat: &gst::Element,
+        offset_applied: &Arc<AtomicBool>,
+    ) -> Result<()> {
+        let index = match self.items.iter().position(|item| &item.video_pad == active) {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+
+        for item in self.items.drain(..index).collect::<Vec<Item>>() {
+            self.remove_item(item)?;
+        }
+
+        self.fill(audio_concat, video_concat, offset_applied)
+    }
+
+    fn remove_item(&self, item: Item) -> Result<()> {
+        item.source.set_state(gst::State::Null)?;
+        if let Some(pipeline) = self.pipeline.as_ref() {
+            pipeline.remove(&item.source)?;
+        }
+        for pad in [&item.audio_pad, &item.video_pad].iter() {
+            if let Some(concat) = pad.get_parent_element() {
+                concat.release_request_pad(*pad);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Ends the playlist item an error was posted from, as an item which fails to decode never ends by
+/// itself and would stall the playlist. Errors of other elements are ignored.
+pub fn end_failed_item(src: &gst::Object) {
+    let mut object = Some(src.clone());
+    while let Some(current) = object {
+        let ghost_pads: Vec<gst::Pad> = match current.downcast_ref::<gst::Bin>() {
+            Some(bin) => ["audio", "video"]
+                .iter()
+                .filter_map(|name| bin.get_static_pad(name))
+                .filter(|pad| pad.is::<gst::GhostPad>())
+                .collect(),
+            None => vec![],
+        };
+        if ghost_pads.len() == 2 {
+            tracing::warn!("Skipping playlist item {}", current.get_name());
+            for pad in ghost_pads {
+                pad.push_event(gst::event::Eos::new());
+            }
+            return;
+        }
+        object = current.get_parent();
+    }
+}
+
+/// Shuffles the URIs with a xorshift generator seeded from the clock, which is plenty to vary the
+/// order of a playlist.
+fn shuffle(uris: &mut [String]) {
+    let mut seed = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or(0)
+        | 1;
+
+    for i in (1..uris.len()).rev() {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        uris.swap(i, (seed % (i as u64 + 1)) as usize);
+    }
+}
@@ -0,0 +1,679 @@
+use super::Config;
+use crate::{
+    gst_create_element, mixer, AudioMode, BarColor, ChromaKeyConfig, ColorConfig, CompressorConfig,
+    CropConfig, EqualizerConfig, MixerAudioConfig, NoiseGateConfig, ReplayConfig, Result,
+    ScalingMode, VideoConfig, VideoFlip, VideoRotation,
+};
+
+use gst::prelude::*;
+use gstreamer as gst;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+/// Name of the replay source capturing the program output of the mixer.
+pub const PROGRAM: &str = "program";
+
+/// Longest rolling buffer a replay may keep. Frames are held in memory, at the default size and
+/// quality five minutes take around a gigabyte.
+pub const MAX_DURATION_S: u64 = 300;
+
+/// Rolling buffer of a replay, oldest frame first.
+type Frames = Arc<Mutex<VecDeque<Frame>>>;
+
+/// JPEG encoded frame of the rolling buffer, timestamped with the running time of the mixer at
+/// which it was captured.
+struct Frame {
+    running_time: u64,
+    buffer: gst::Buffer,
+}
+
+/// State of a [`Replay`](struct.Replay.html) input.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ReplayStatus {
+    pub source: String,
+    pub buffered_ms: u64,
+    pub playing: bool,
+}
+
+/// Input keeping a rolling buffer of the program or another input, and playing marked segments of
+/// it back into the mixer at normal or reduced speed.
+pub struct Replay {
+    pub name: String,
+    /// Input the buffer is recorded from, or [`PROGRAM`](constant.PROGRAM.html).
+    pub source: String,
+    config: Config,
+    pipeline: Option<gst::Pipeline>,
+    frames: Frames,
+    capture: gst::Pipeline,
+    capture_src: gst::Element,
+    capture_probe: Mutex<Option<(gst::Pad, gst::PadProbeId)>>,
+    generation: Arc<AtomicUsize>,
+    playing: Arc<AtomicBool>,
+    audio: gst::Element,
+    audio_convert: gst::Element,
+    audio_resample: gst::Element,
+    audio_capsfilter: gst::Element,
+    audio_queue: gst::Element,
+    video: gst::Element,
+    video_decode: gst::Element,
+    video_convert: gst::Element,
+    video_crop: gst::Element,
+    video_flip: gst::Element,
+    video_balance: gst::Element,
+    video_scale: gst::Element,
+    video_rate: gst::Element,
+    video_capsfilter: gst::Element,
+    video_tee: gst::Element,
+    video_queue: gst::Element,
+}
+
+impl Replay {
+    pub fn create(config: Config, source: &str, replay: ReplayConfig) -> Result<Self> {
+        if replay.duration_s == 0 || replay.width <= 0 || replay.height <= 0 {
+            return Err(mixer::Error::InvalidConfig(
+                "replay buffer duration and size must be positive".to_string(),
+            ));
+        }
+        if replay.duration_s > MAX_DURATION_S {
+            return Err(mixer::Error::InvalidConfig(format!(
+                "replay buffer duration must be at most {} seconds",
+                MAX_DURATION_S
+            )));
+        }
+
+        let (capture, capture_src, frames) = create_capture(&config.name, &replay)?;
+
+        // Frames are timestamped as they are pushed, which paces slow motion playback.
+        let video = gst_create_element("appsrc", &format!("input_{}_appsrc", config.name))?;
+        video.set_property("is-live", &true)?;
+        video.set_property("do-timestamp", &true)?;
+        video.set_property_from_str("format", "time");
+        video.set_property("caps", &gst::Caps::new_simple("image/jpeg", &[]))?;
+        let video_decode =
+            gst_create_element("jpegdec", &format!("input_{}_video_decode", config.name))?;
+        let video_convert = gst_create_element(
+            "videoconvert",
+            &format!("input_{}_video_convert", config.name),
+        )?;
+        let video_crop =
+            gst_create_element("videocrop", &format!("input_{}_video_crop", config.name))?;
+        super::set_crop_properties(&video_crop, &config.video.crop)?;
+        let video_flip =
+            gst_create_element("videoflip", &format!("input_{}_video_flip", config.name))?;
+        super::set_flip_properties(
+            &video_flip,
+            config.video.flip.as_ref(),
+            config.video.rotation.as_ref(),
+        );
+        let video_balance = gst_create_element(
+            "videobalance",
+            &format!("input_{}_video_balance", config.name),
+        )?;
+        super::set_color_properties(&video_balance, &config.video.color)?;
+        let video_scale =
+            gst_create_element("videoscale", &format!("input_{}_video_scale", config.name))?;
+        let video_rate =
+            gst_create_element("videorate", &format!("input_{}_video_rate", config.name))?;
+        let video_capsfilter = gst_create_element(
+            "capsfilter",
+            &format!("input_{}_video_capsfilter", config.name),
+        )?;
+        let video_caps = gst::Caps::builder("video/x-raw")
+            .field("framerate", &gst::Fraction::new(config.video.framerate, 1))
+            .field("width", &config.video.width)
+            .field("height", &config.video.height)
+            .field("format", &config.video.format.to_string())
+            .build();
+        video_capsfilter.set_property("caps", &video_caps)?;
+        let video_tee = gst_create_element("tee", &format!("input_{}_video_tee", config.name))?;
+        video_tee.set_property("allow-not-linked", &true)?;
+        let video_queue =
+            gst_create_element("queue", &format!("input_{}_video_queue", config.name))?;
+
+        // Replays are silent.
+        let audio = gst_create_element(
+            "audiotestsrc",
+            &format!("input_{}_audiotestsrc", config.name),
+        )?;
+        audio.set_property_from_str("wave", "silence");
+        audio.set_property("is-live", &true)?;
+        let audio_convert = gst_create_element(
+            "audioconvert",
+            &format!("input_{}_audio_convert", config.name),
+        )?;
+        let audio_resample = gst_create_element(
+            "audioresample",
+            &format!("input_{}_audio_resample", config.name),
+        )?;
+        let audio_capsfilter = gst_create_element(
+            "capsfilter",
+            &format!("input_{}_audio_capsfilter", config.name),
+        )?;
+        audio_capsfilter.set_property("caps", &MixerAudioConfig::default().caps())?;
+        let audio_queue =
+            gst_create_element("queue", &format!("input_{}_audio_queue", config.name))?;
+
+        Ok(Replay {
+            name: config.name.clone(),
+            source: source.to_string(),
+            config,
+            pipeline: None,
+            frames,
+            capture,
+            capture_src,
+            capture_probe: Mutex::new(None),
+            generation: Arc::new(AtomicUsize::new(0)),
+            playing: Arc::new(AtomicBool::new(false)),
+            audio,
+            audio_convert,
+            audio_resample,
+            audio_capsfilter,
+            audio_queue,
+            video,
+            video_decode,
+            video_convert,
+            video_crop,
+            video_flip,
+            video_balance,
+            video_scale,
+            video_rate,
+            video_capsfilter,
+            video_tee,
+            video_queue,
+        })
+    }
+
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// Starts buffering the video flowing through `pad`, which belongs to the source of the
+    /// replay.
+    pub fn attach(&mut self, pad: gst::Pad) -> Result<()> {
+        let probe = super::tap_pad(&pad, &self.capture_src, 0)?;
+
+        if let Ok(mut capture_probe) = self.capture_probe.lock() {
+            *capture_probe = Some((pad, probe));
+        }
+        self.capture.set_state(gst::State::Playing)?;
+        Ok(())
+    }
+
+    pub fn link(
+        &mut self,
+        pipeline: gst::Pipeline,
+        audio: gst::Element,
+        video: gst::Element,
+    ) -> Result<()> {
+        pipeline.add_many(&[
+            &self.video,
+            &self.video_decode,
+            &self.video_convert,
+            &self.video_crop,
+            &self.video_flip,
+            &self.video_balance,
+            &self.video_scale,
+            &self.video_rate,
+            &self.video_capsfilter,
+            &self.video_tee,
+            &self.video_queue,
+            &self.audio,
+            &self.audio_convert,
+            &self.audio_resample,
+            &self.audio_capsfilter,
+            &self.audio_queue,
+        ])?;
+
+        self.pipeline = Some(pipeline);
+
+        gst::Element::link_many(&[
+            &self.video,
+            &self.video_decode,
+            &self.video_convert,
+            &self.video_crop,
+            &self.video_flip,
+            &self.video_balance,
+            &self.video_scale,
+            &self.video_rate,
+            &self.video_capsfilter,
+            &self.video_tee,
+            &self.video_queue,
+            &video,
+        ])?;
+
+        gst::Element::link_many(&[
+            &self.audio,
+            &self.audio_convert,
+            &self.audio_resample,
+            &self.audio_capsfilter,
+            &self.audio_queue,
+            &audio,
+        ])?;
+
+        Ok(())
+    }
+
+    pub fn unlink(&self) -> Result<()> {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        if let Some((pad, probe)) = self.capture_probe.lock().ok().and_then(|mut p| p.take()) {
+            pad.remove_probe(probe);
+        }
+        self.capture.set_state(gst::State::Null)?;
+
+        super::release_request_pad(&self.audio_queue)?;
+        super::release_request_pad(&self.video_queue)?;
+
+        if let Some(pipeline) = self.pipeline.as_ref() {
+            pipeline.remove_many(&[
+                &self.video,
+                &self.video_decode,
+                &self.video_convert,
+                &self.video_crop,
+                &self.video_flip,
+                &self.video_balance,
+                &self.video_scale,
+                &self.video_rate,
+                &self.video_capsfilter,
+                &self.video_tee,
+                &self.video_queue,
+                &self.audio,
+                &self.audio_convert,
+                &self.audio_resample,
+                &self.audio_capsfilter,
+                &self.audio_queue,
+            ])?;
+        }
+        Ok(())
+    }
+
+    pub fn set_state(&mut self, state: gst::State) -> Result<()> {
+        self.audio.set_state(state)?;
+        self.audio_convert.set_state(state)?;
+        self.audio_resample.set_state(state)?;
+        self.audio_capsfilter.set_state(state)?;
+        self.audio_queue.set_state(state)?;
+        self.video.set_state(state)?;
+        self.video_decode.set_state(state)?;
+        self.video_convert.set_state(state)?;
+        self.video_crop.set_state(state)?;
+        self.video_flip.set_state(state)?;
+        self.video_balance.set_state(state)?;
+        self.video_scale.set_state(state)?;
+        self.video_rate.set_state(state)?;
+        self.video_capsfilter.set_state(state)?;
+        self.video_tee.set_state(state)?;
+        self.video_queue.set_state(state)?;
+        Ok(())
+    }
+
+    /// Plays back the buffered video between `mark_in_ms` and `mark_out_ms` before the most
+    /// recent frame, slowed down by `speed`. Replaces any replay in progress.
+    pub fn play(&self, mark_in_ms: u64, mark_out_ms: u64, speed: f64) -> Result<()> {
+        if mark_in_ms <= mark_out_ms {
+            return Err(mixer::Error::InvalidConfig(
+                "mark in must be before mark out".to_string(),
+            ));
+        }
+        if !(speed > 0.0 && speed <= 1.0) {
+            return Err(mixer::Error::InvalidConfig(
+                "speed must be greater than 0 and at most 1".to_string(),
+            ));
+        }
+
+        let segment: Vec<(u64, gst::Buffer)> = {
+            let frames = self.frames.lock().map_err(|_| mixer::Error::Unknown)?;
+            let latest = match frames.back() {
+                Some(frame) => frame.running_time,
+                None => 0,
+            };
+            let start = latest.saturating_sub(mark_in_ms.saturating_mul(gst::MSECOND_VAL));
+            let end = latest.saturating_sub(mark_out_ms.saturating_mul(gst::MSECOND_VAL));
+            frames
+                .iter()
+                .filter(|frame| frame.running_time >= start && frame.running_time <= end)
+                .map(|frame| (frame.running_time, frame.buffer.clone()))
+                .collect()
+        };
+
+        if segment.is_empty() {
+            return Err(mixer::Error::InvalidConfig(
+                "no frames buffered between the marks".to_string(),
+            ));
+        }
+
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let current = self.generation.clone();
+        let playing = self.playing.clone();
+        let video = self.video.clone();
+        playing.store(true, Ordering::SeqCst);
+
+        std::thread::spawn(move || {
+            let mut previous = segment[0].0;
+            for (running_time, buffer) in segment {
+                let delay = running_time.saturating_sub(previous) as f64 / speed;
+                std::thread::sleep(Duration::from_nanos(delay as u64));
+                previous = running_time;
+
+                if current.load(Ordering::SeqCst) != generation
+                    || video.emit("push-buffer", &[&buffer]).is_err()
+                {
+                    return;
+                }
+            }
+
+            if current.load(Ordering::SeqCst) == generation {
+                playing.store(false, Ordering::SeqCst);
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Stops the replay in progress, holding its current frame.
+    pub fn stop(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.playing.store(false, Ordering::SeqCst);
+    }
+
+    pub fn status(&self) -> ReplayStatus {
+        let buffered_ms = match self.frames.lock() {
+            Ok(frames) => match (frames.front(), frames.back()) {
+                (Some(first), Some(last)) => {
+                    last.running_time.saturating_sub(first.running_time) / gst::MSECOND_VAL
+                }
+                _ => 0,
+            },
+            Err(_) => 0,
+        };
+
+        ReplayStatus {
+            source: self.source.clone(),
+            buffered_ms,
+            playing: self.playing.load(Ordering::SeqCst),
+        }
+    }
+
+    pub fn set_volume(&mut self, _volume: f64, _update_config: bool) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_mute(&mut self, _mute: bool, _update_config: bool) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_gain(&mut self, _gain: f64, _update_config: bool) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_equalizer(
+        &mut self,
+        _equalizer: EqualizerConfig,
+        _update_config: bool,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_compressor(
+        &mut self,
+        _compressor: Option<CompressorConfig>,
+        _update_config: bool,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_noise_gate(
+        &mut self,
+        _noise_gate: Option<NoiseGateConfig>,
+        _update_config: bool,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_av_offset(&mut self, _av_offset_ms: i64, _update_config: bool) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_audio_mode(&mut self, mode: AudioMode, update_config: bool) -> Result<()> {
+        if update_config {
+            self.config.audio.mode = mode;
+        }
+        Ok(())
+    }
+
+    pub fn set_audio_format(&mut self, audio: &MixerAudioConfig) -> Result<()> {
+        self.audio_capsfilter.set_property("caps", &audio.caps())?;
+        Ok(())
+    }
+
+    pub fn set_zorder(&mut self, zorder: u32, update_config: bool) -> Result<()> {
+        if update_config {
+            self.config.video.zorder = Some(zorder);
+        }
+        self.set_compositor_property("zorder", &zorder)
+    }
+
+    pub fn set_width(&mut self, width: i32, update_config: bool) -> Result<()> {
+        if update_config {
+            self.config.video.width = width;
+        }
+        self.set_compositor_property("width", &width)
+    }
+
+    pub fn set_height(&mut self, height: i32, update_config: bool) -> Result<()> {
+        if update_config {
+            self.config.video.height = height;
+        }
+        self.set_compositor_property("height", &height)
+    }
+
+    pub fn set_xpos(&mut self, xpos: i32, update_config: bool) -> Result<()> {
+        if update_config {
+            self.config.video.xpos = xpos;
+        }
+        self.set_compositor_property("xpos", &xpos)
+    }
+
+    pub fn set_ypos(&mut self, ypos: i32, update_config: bool) -> Result<()> {
+        if update_config {
+            self.config.video.ypos = ypos;
+        }
+        self.set_compositor_property("ypos", &ypos)
+    }
+
+    pub fn set_alpha(&mut self, alpha: f64, update_config: bool) -> Result<()> {
+        if update_config {
+            self.config.video.alpha = alpha;
+        }
+        self.set_compositor_property("alpha", &alpha)
+    }
+
+    pub fn set_chroma_key(
+        &mut self,
+        chroma_key: Option<ChromaKeyConfig>,
+        _update_config: bool,
+    ) -> Result<()> {
+        super::unsupported("chroma key", &chroma_key, &None)
+    }
+
+    pub fn set_crop(&mut self, crop: CropConfig, update_config: bool) -> Result<()> {
+        super::set_crop_properties(&self.video_crop, &crop)?;
+        if update_config {
+            self.config.video.crop = crop;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_flip(&mut self, flip: Option<VideoFlip>, update_config: bool) -> Result<()> {
+        super::set_flip_properties(
+            &self.video_flip,
+            flip.as_ref(),
+            self.config.video.rotation.as_ref(),
+        );
+        if update_config {
+            self.config.video.flip = flip;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_rotation(
+        &mut self,
+        rotation: Option<VideoRotation>,
+        update_config: bool,
+    ) -> Result<()> {
+        super::set_flip_properties(
+            &self.video_flip,
+            self.config.video.flip.as_ref(),
+            rotation.as_ref(),
+        );
+        if update_config {
+            self.config.video.rotation = rotation;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_scaling_mode(
+        &mut self,
+        scaling_mode: ScalingMode,
+        _update_config: bool,
+    ) -> Result<()> {
+        super::unsupported(
+            "scaling mode",
+            &scaling_mode,
+            &VideoConfig::default().scaling_mode,
+        )
+    }
+
+    pub fn set_bar_color(&mut self, bar_color: BarColor, _update_config: bool) -> Result<()> {
+        super::unsupported("bar color", &bar_color, &VideoConfig::default().bar_color)
+    }
+
+    pub fn set_color(&mut self, color: ColorConfig, update_config: bool) -> Result<()> {
+        super::set_color_properties(&self.video_balance, &color)?;
+        if update_config {
+            self.config.video.color = color;
+        }
+
+        Ok(())
+    }
+
+    pub fn video_pad(&self) -> Option<gst::Pad> {
+        self.video_queue
+            .get_static_pad("src")
+            .and_then(|pad| pad.get_peer())
+    }
+
+    pub fn video_tee(&self) -> gst::Element {
+        self.video_tee.clone()
+    }
+
+    pub fn audio_pad(&self) -> Option<gst::Pad> {
+        self.audio_queue
+            .get_static_pad("src")
+            .and_then(|pad| pad.get_peer())
+    }
+
+    pub fn config(&self) -> Config {
+        self.config.clone()
+    }
+
+    fn set_compositor_property(&self, property: &str, value: &dyn ToValue) -> Result<()> {
+        super::set_peer_pad_property(
+            &self
+                .video_queue
+                .get_static_pad("src")
+                .ok_or_else(|| mixer::Error::Gstreamer("failed to retrieve src pad".to_string()))?,
+            property,
+            value,
+        )
+    }
+}
+
+/// Creates the pipeline encoding the frames of the replay source into the rolling buffer. It runs
+/// separately from the mixer so encoding never holds up the program.
+fn create_capture(
+    name: &str,
+    replay: &ReplayConfig,
+) -> Result<(gst::Pipeline, gst::Element, Frames)> {
+    let pipeline = gst::Pipeline::new(Some(&format!("replay_{}", name)));
+
+    let src = gst_create_element("appsrc", &format!("replay_{}_appsrc", name))?;
+    src.set_property_from_str("format", "time");
+    let queue = gst_create_element("queue", &format!("replay_{}_queue", name))?;
+    // Drop frames rather than falling behind when encoding cannot keep up.
+    queue.set_property_from_str("leaky", "downstream");
+    let convert = gst_create_element("videoconvert", &format!("replay_{}_convert", name))?;
+    let scale = gst_create_element("videoscale", &format!("replay_{}_scale", name))?;
+    let capsfilter = gst_create_element("capsfilter", &format!("replay_{}_capsfilter", name))?;
+    let caps = gst::Caps::builder("video/x-raw")
+        .field("width", &replay.width)
+        .field("height", &replay.height)
+        .field("pixel-aspect-ratio", &gst::Fraction::new(1, 1))
+        .build();
+    capsfilter.set_property("caps", &caps)?;
+    let encoder = gst_create_element("jpegenc", &format!("replay_{}_jpegenc", name))?;
+    encoder.set_property("quality", &replay.quality)?;
+    let sink = gst_create_element("appsink", &format!("replay_{}_appsink", name))?;
+    sink.set_property("sync", &false)?;
+    sink.set_property("emit-signals", &true)?;
+
+    let elements = [&src, &queue, &convert, &scale, &capsfilter, &encoder, &sink];
+    pipeline.add_many(&elements)?;
+    gst::Element::link_many(&elements)?;
+
+    let frames = Arc::new(Mutex::new(VecDeque::new()));
+    let duration = replay.duration_s.saturating_mul(gst::SECOND_VAL);
+    let buffer_frames = frames.clone();
+    sink.connect("new-sample", false, move |args| {
+        if let Some(sink) = args[0].get::<gst::Element>().ok().flatten() {
+            store_frame(&sink, &buffer_frames, duration);
+        }
+        Some(gst::FlowReturn::Ok.to_value())
+    })
+    .map_err(|e| mixer::Error::Gstreamer(e.to_string()))?;
+
+    Ok((pipeline, src, frames))
+}
+
+/// Appends the next sample of the capture appsink to the buffer, dropping frames older than
+/// `duration` nanoseconds.
+fn store_frame(sink: &gst::Element, frames: &Mutex<VecDeque<Frame>>, duration: u64) -> Option<()> {
+    let sample = sink
+        .emit("pull-sample", &[])
+        .ok()??
+        .get::<gst::Sample>()
+        .ok()??;
+    let mut buffer = sample.get_buffer_owned()?;
+    let running_time = buffer.get_pts().nseconds()?;
+
+    // Playback timestamps the frames again as they are pushed.
+    {
+        let buffer = buffer.make_mut();
+        buffer.set_pts(gst::ClockTime::none());
+        buffer.set_dts(gst::ClockTime::none());
+    }
+
+    let mut frames = frames.lock().ok()?;
+    frames.push_back(Frame {
+        running_time,
+        buffer,
+    });
+    while frames
+        .front()
+        .map(|frame| running_time.saturating_sub(frame.running_time) > duration)
+        .unwrap_or(false)
+    {
+        frames.pop_front();
+    }
+
+    Some(())
+}
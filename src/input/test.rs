@@ -1,6 +1,10 @@
 use super::Config;
 use crate::mixer::Error as MixerError;
-use crate::{gst_create_element, Result};
+use crate::{
+    gst_create_element, AudioMode, BarColor, ChromaKeyConfig, ColorConfig, CompressorConfig,
+    CropConfig, EqualizerConfig, MixerAudioConfig, NoiseGateConfig, Result, ScalingMode, VideoFlip,
+    VideoRotation,
+};
 
 use gst::prelude::*;
 use gstreamer as gst;
@@ -9,15 +13,25 @@ pub struct Test {
     pub name: String,
     pipeline: Option<gst::Pipeline>,
     config: Config,
+    /// Volume and mute currently applied to the tone, which may differ from the config while
+    /// the input is silenced by its audio mode.
+    volume: f64,
+    mute: bool,
     audio: gst::Element,
     audio_convert: gst::Element,
     audio_resample: gst::Element,
+    audio_capsfilter: gst::Element,
     audio_queue: gst::Element,
     video: gst::Element,
     video_convert: gst::Element,
+    video_crop: gst::Element,
+    video_flip: gst::Element,
+    video_balance: gst::Element,
     video_scale: gst::Element,
     video_rate: gst::Element,
     video_capsfilter: gst::Element,
+    video_tee: gst::Element,
+    video_queue: gst::Element,
 }
 
 impl Test {
@@ -34,6 +48,21 @@ impl Test {
             "videoconvert",
             &format!("input_{}_video_convert", config.name),
         )?;
+        let video_crop =
+            gst_create_element("videocrop", &format!("input_{}_video_crop", config.name))?;
+        super::set_crop_properties(&video_crop, &config.video.crop)?;
+        let video_flip =
+            gst_create_element("videoflip", &format!("input_{}_video_flip", config.name))?;
+        super::set_flip_properties(
+            &video_flip,
+            config.video.flip.as_ref(),
+            config.video.rotation.as_ref(),
+        );
+        let video_balance = gst_create_element(
+            "videobalance",
+            &format!("input_{}_video_balance", config.name),
+        )?;
+        super::set_color_properties(&video_balance, &config.video.color)?;
         let video_scale =
             gst_create_element("videoscale", &format!("input_{}_video_scale", config.name))?;
         let video_rate =
@@ -49,12 +78,19 @@ impl Test {
             .field("format", &config.video.format.to_string())
             .build();
         video_capsfilter.set_property("caps", &video_caps)?;
+        let video_tee = gst_create_element("tee", &format!("input_{}_video_tee", config.name))?;
+        video_tee.set_property("allow-not-linked", &true)?;
+        let video_queue =
+            gst_create_element("queue", &format!("input_{}_video_queue", config.name))?;
 
         let audio = gst_create_element(
             "audiotestsrc",
             &format!("input_{}_audiotestsrc", config.name),
         )?;
-        audio.set_property("volume", &config.audio.volume)?;
+        audio.set_property(
+            "volume",
+            &tone_volume(config.audio.volume, config.audio.mute),
+        )?;
         audio.set_property("is-live", &true)?;
         let audio_queue =
             gst_create_element("queue", &format!("input_{}_audio_queue", config.name))?;
@@ -66,20 +102,33 @@ impl Test {
             "audioresample",
             &format!("input_{}_audio_resample", config.name),
         )?;
+        let audio_capsfilter = gst_create_element(
+            "capsfilter",
+            &format!("input_{}_audio_capsfilter", config.name),
+        )?;
+        audio_capsfilter.set_property("caps", &MixerAudioConfig::default().caps())?;
 
         Ok(Test {
             name: config.name.clone(),
             pipeline: None,
+            volume: config.audio.volume,
+            mute: config.audio.mute,
             config,
             audio,
             audio_queue,
             audio_resample,
+            audio_capsfilter,
             audio_convert,
             video,
             video_convert,
+            video_crop,
+            video_flip,
+            video_balance,
             video_rate,
             video_scale,
             video_capsfilter,
+            video_tee,
+            video_queue,
         })
     }
 
@@ -96,12 +145,18 @@ impl Test {
         pipeline.add_many(&[
             &self.video,
             &self.video_convert,
+            &self.video_crop,
+            &self.video_flip,
+            &self.video_balance,
             &self.video_scale,
             &self.video_rate,
             &self.video_capsfilter,
+            &self.video_tee,
+            &self.video_queue,
             &self.audio,
             &self.audio_convert,
             &self.audio_resample,
+            &self.audio_capsfilter,
             &self.audio_queue,
         ])?;
 
@@ -111,9 +166,14 @@ impl Test {
         gst::Element::link_many(&[
             &self.video,
             &self.video_convert,
+            &self.video_crop,
+            &self.video_flip,
+            &self.video_balance,
             &self.video_scale,
             &self.video_rate,
             &self.video_capsfilter,
+            &self.video_tee,
+            &self.video_queue,
             &video,
         ])?;
 
@@ -122,6 +182,7 @@ impl Test {
             &self.audio,
             &self.audio_convert,
             &self.audio_resample,
+            &self.audio_capsfilter,
             &self.audio_queue,
             &audio,
         ])?;
@@ -131,18 +192,24 @@ impl Test {
 
     pub fn unlink(&self) -> Result<()> {
         super::release_request_pad(&self.audio)?;
-        super::release_request_pad(&self.video)?;
+        super::release_request_pad(&self.video_queue)?;
 
         if let Some(pipeline) = self.pipeline.as_ref() {
             pipeline.remove_many(&[
                 &self.video,
                 &self.video_convert,
+                &self.video_crop,
+                &self.video_flip,
+                &self.video_balance,
                 &self.video_scale,
                 &self.video_rate,
                 &self.video_capsfilter,
+                &self.video_tee,
+                &self.video_queue,
                 &self.audio,
                 &self.audio_convert,
                 &self.audio_resample,
+                &self.audio_capsfilter,
                 &self.audio_queue,
             ])?;
         }
@@ -153,23 +220,89 @@ impl Test {
         self.audio.set_state(state)?;
         self.audio_convert.set_state(state)?;
         self.audio_resample.set_state(state)?;
+        self.audio_capsfilter.set_state(state)?;
         self.audio_queue.set_state(state)?;
         self.video.set_state(state)?;
         self.video_convert.set_state(state)?;
+        self.video_crop.set_state(state)?;
+        self.video_flip.set_state(state)?;
+        self.video_balance.set_state(state)?;
         self.video_scale.set_state(state)?;
         self.video_rate.set_state(state)?;
         self.video_capsfilter.set_state(state)?;
+        self.video_tee.set_state(state)?;
+        self.video_queue.set_state(state)?;
         Ok(())
     }
 
-    pub fn set_volume(&mut self, _volume: f64, _update_config: bool) -> Result<()> {
+    pub fn set_volume(&mut self, volume: f64, update_config: bool) -> Result<()> {
+        if update_config {
+            self.config.audio.volume = volume;
+        }
+        self.volume = volume;
+        self.audio
+            .set_property("volume", &tone_volume(self.volume, self.mute))?;
+        Ok(())
+    }
+
+    pub fn set_mute(&mut self, mute: bool, update_config: bool) -> Result<()> {
+        if update_config {
+            self.config.audio.mute = mute;
+        }
+        self.mute = mute;
+        self.audio
+            .set_property("volume", &tone_volume(self.volume, self.mute))?;
+        Ok(())
+    }
+
+    pub fn set_gain(&mut self, _gain: f64, _update_config: bool) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_equalizer(
+        &mut self,
+        _equalizer: EqualizerConfig,
+        _update_config: bool,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_compressor(
+        &mut self,
+        _compressor: Option<CompressorConfig>,
+        _update_config: bool,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_noise_gate(
+        &mut self,
+        _noise_gate: Option<NoiseGateConfig>,
+        _update_config: bool,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_av_offset(&mut self, _av_offset_ms: i64, _update_config: bool) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_audio_mode(&mut self, mode: AudioMode, update_config: bool) -> Result<()> {
+        if update_config {
+            self.config.audio.mode = mode;
+        }
+        Ok(())
+    }
+
+    pub fn set_audio_format(&mut self, audio: &MixerAudioConfig) -> Result<()> {
+        self.audio_capsfilter.set_property("caps", &audio.caps())?;
         Ok(())
     }
 
     pub fn set_zorder(&mut self, zorder: u32, _update_config: bool) -> Result<()> {
         super::set_peer_pad_property(
             &self
-                .video_capsfilter
+                .video_queue
                 .get_static_pad("src")
                 .ok_or_else(|| MixerError::Gstreamer("Failed to get static src pad".to_string()))?,
             "zorder",
@@ -199,7 +332,105 @@ impl Test {
         Ok(())
     }
 
+    pub fn set_crop(&mut self, crop: CropConfig, update_config: bool) -> Result<()> {
+        super::set_crop_properties(&self.video_crop, &crop)?;
+        if update_config {
+            self.config.video.crop = crop;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_flip(&mut self, flip: Option<VideoFlip>, update_config: bool) -> Result<()> {
+        super::set_flip_properties(
+            &self.video_flip,
+            flip.as_ref(),
+            self.config.video.rotation.as_ref(),
+        );
+        if update_config {
+            self.config.video.flip = flip;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_rotation(
+        &mut self,
+        rotation: Option<VideoRotation>,
+        update_config: bool,
+    ) -> Result<()> {
+        super::set_flip_properties(
+            &self.video_flip,
+            self.config.video.flip.as_ref(),
+            rotation.as_ref(),
+        );
+        if update_config {
+            self.config.video.rotation = rotation;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_color(&mut self, color: ColorConfig, update_config: bool) -> Result<()> {
+        super::set_color_properties(&self.video_balance, &color)?;
+        if update_config {
+            self.config.video.color = color;
+        }
+
+        Ok(())
+    }
+
+    /// There is no `alpha` element to key the test pattern with, the chroma key is only kept in
+    /// the config.
+    pub fn set_chroma_key(
+        &mut self,
+        chroma_key: Option<ChromaKeyConfig>,
+        update_config: bool,
+    ) -> Result<()> {
+        if update_config {
+            self.config.video.chroma_key = chroma_key;
+        }
+        Ok(())
+    }
+
+    pub fn set_scaling_mode(
+        &mut self,
+        _scaling_mode: ScalingMode,
+        _update_config: bool,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_bar_color(&mut self, _bar_color: BarColor, _update_config: bool) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn video_pad(&self) -> Option<gst::Pad> {
+        self.video_queue
+            .get_static_pad("src")
+            .and_then(|pad| pad.get_peer())
+    }
+
+    pub fn video_tee(&self) -> gst::Element {
+        self.video_tee.clone()
+    }
+
+    pub fn audio_pad(&self) -> Option<gst::Pad> {
+        self.audio_queue
+            .get_static_pad("src")
+            .and_then(|pad| pad.get_peer())
+    }
+
     pub fn config(&self) -> Config {
         self.config.clone()
     }
 }
+
+/// `audiotestsrc` has no mute of its own, so muting silences the tone through its volume.
+fn tone_volume(volume: f64, mute: bool) -> f64 {
+    if mute {
+        0.0
+    } else {
+        volume
+    }
+}
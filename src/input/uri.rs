@@ -1,12 +1,14 @@
 use super::Config;
 use crate::{
-    gst_create_element, mixer,
-    output::{Config as OutputConfig, EncoderConfig, File as FileOutput},
-    AudioEncoder, AudioEncoderConfig, Mux, Result, VideoEncoder, VideoEncoderConfig,
+    gst_create_element, mixer, snapshot::Snapshot, AudioMode, BarColor, ChromaKeyConfig,
+    ColorConfig, CompressorConfig, CropConfig, EqualizerConfig, MixerAudioConfig, NoiseGateConfig,
+    PlaybackConfig, Result, ScalingMode, VideoFlip, VideoRotation,
 };
 
 use gst::prelude::*;
 use gstreamer as gst;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
 
 pub struct URI {
     pub name: String,
@@ -18,16 +20,46 @@ pub struct URI {
     audio_tee_queue: gst::Element,
     audio_convert: gst::Element,
     audio_resample: gst::Element,
+    audio_format_convert: gst::Element,
+    audio_capsfilter: gst::Element,
+    audio_gain: gst::Element,
+    audio_equalizer: gst::Element,
+    audio_gate: gst::Element,
+    audio_compressor: gst::Element,
     audio_volume: gst::Element,
     audio_queue: gst::Element,
     video_tee: gst::Element,
     video_tee_queue: gst::Element,
+    snapshot: Snapshot,
     video_convert: gst::Element,
+    video_crop: gst::Element,
+    video_flip: gst::Element,
+    video_balance: gst::Element,
     video_scale: gst::Element,
     video_rate: gst::Element,
+    video_alpha: gst::Element,
+    video_box: gst::Element,
     video_capsfilter: gst::Element,
     video_queue: gst::Element,
-    record_output: Option<FileOutput>,
+    scaling: Arc<Mutex<Scaling>>,
+    paused_at: Option<gst::ClockTime>,
+}
+
+/// Scaling applied by the video box, shared with the probe reconfiguring it whenever the size of
+/// the cropped and rotated video changes.
+struct Scaling {
+    mode: ScalingMode,
+    bar_color: BarColor,
+}
+
+/// Playback state of a [`URI`](struct.URI.html) input.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PlaybackStatus {
+    pub paused: bool,
+    pub position_ms: Option<u64>,
+    pub duration_ms: Option<u64>,
+    pub in_point_ms: Option<u64>,
+    pub out_point_ms: Option<u64>,
 }
 
 impl URI {
@@ -47,15 +79,41 @@ impl URI {
             "videoconvert",
             &format!("input_{}_video_convert", config.name),
         )?;
+        let video_crop =
+            gst_create_element("videocrop", &format!("input_{}_video_crop", config.name))?;
+        super::set_crop_properties(&video_crop, &config.video.crop)?;
+        let video_flip =
+            gst_create_element("videoflip", &format!("input_{}_video_flip", config.name))?;
+        super::set_flip_properties(
+            &video_flip,
+            config.video.flip.as_ref(),
+            config.video.rotation.as_ref(),
+        );
+        let video_balance = gst_create_element(
+            "videobalance",
+            &format!("input_{}_video_balance", config.name),
+        )?;
+        super::set_color_properties(&video_balance, &config.video.color)?;
         let video_scale =
             gst_create_element("videoscale", &format!("input_{}_video_scale", config.name))?;
+        // Borders are added by video_box according to the scaling mode.
+        video_scale.set_property("add-borders", &false)?;
         let video_rate =
             gst_create_element("videorate", &format!("input_{}_video_rate", config.name))?;
+        let video_alpha =
+            gst_create_element("alpha", &format!("input_{}_video_alpha", config.name))?;
+        set_chroma_key_properties(&video_alpha, config.video.chroma_key.as_ref())?;
+        let video_box =
+            gst_create_element("videobox", &format!("input_{}_video_box", config.name))?;
+
+        // The alpha element always outputs a format carrying alpha, this allows the chroma key to
+        // be enabled or disabled without renegotiating caps.
         let video_caps = gst::Caps::builder("video/x-raw")
             .field("framerate", &gst::Fraction::new(config.video.framerate, 1))
-            .field("format", &config.video.format.to_string())
+            .field("format", &config.video.format.with_alpha().to_string())
             .field("width", &config.video.width)
             .field("height", &config.video.height)
+            .field("pixel-aspect-ratio", &gst::Fraction::new(1, 1))
             .field("colorimetry", &"sRGB")
             .build();
         let video_capsfilter = gst_create_element(
@@ -82,10 +140,87 @@ impl URI {
             "audioresample",
             &format!("input_{}_audio_resample", config.name),
         )?;
+        // The dynamics and equalizer elements only handle a few sample formats, the audio is
+        // converted to the program format once it has been processed.
+        let audio_format_convert = gst_create_element(
+            "audioconvert",
+            &format!("input_{}_audio_format_convert", config.name),
+        )?;
+        let audio_capsfilter = gst_create_element(
+            "capsfilter",
+            &format!("input_{}_audio_capsfilter", config.name),
+        )?;
+        audio_capsfilter.set_property("caps", &MixerAudioConfig::default().caps())?;
 
         let audio_volume =
             gst_create_element("volume", &format!("input_{}_audio_volume", config.name))?;
         audio_volume.set_property("volume", &config.audio.volume)?;
+        audio_volume.set_property("mute", &config.audio.mute)?;
+
+        let audio_gain =
+            gst_create_element("volume", &format!("input_{}_audio_gain", config.name))?;
+        audio_gain.set_property("volume", &super::gain_to_volume(config.audio.gain))?;
+        let audio_equalizer = gst_create_element(
+            "equalizer-3bands",
+            &format!("input_{}_audio_equalizer", config.name),
+        )?;
+        super::set_equalizer_properties(&audio_equalizer, &config.audio.equalizer)?;
+        let audio_gate =
+            gst_create_element("audiodynamic", &format!("input_{}_audio_gate", config.name))?;
+        audio_gate.set_property_from_str("mode", "expander");
+        super::set_noise_gate_properties(&audio_gate, config.audio.noise_gate.as_ref())?;
+        let audio_compressor = gst_create_element(
+            "audiodynamic",
+            &format!("input_{}_audio_compressor", config.name),
+        )?;
+        audio_compressor.set_property_from_str("mode", "compressor");
+        audio_compressor.set_property_from_str("characteristics", "soft-knee");
+        super::set_compressor_properties(&audio_compressor, config.audio.compressor.as_ref())?;
+
+        super::set_av_offset_properties(&audio_convert, &video_convert, config.audio.av_offset_ms)?;
+
+        // The size the video box has to fit is only known once the source is cropped and
+        // rotated, and changes along with the crop and rotation.
+        let scaling = Arc::new(Mutex::new(Scaling {
+            mode: config.video.scaling_mode.clone(),
+            bar_color: config.video.bar_color.clone(),
+        }));
+        let shared_scaling = Arc::clone(&scaling);
+        let vbox = video_box.clone();
+        let (width, height) = (config.video.width, config.video.height);
+        let input_name = config.name.clone();
+        video_flip
+            .get_static_pad("src")
+            .ok_or_else(|| {
+                mixer::Error::Gstreamer("Failed to get src pad of video flip".to_string())
+            })?
+            .add_probe(gst::PadProbeType::EVENT_DOWNSTREAM, move |_, info| {
+                let caps = match &info.data {
+                    Some(gst::PadProbeData::Event(event)) => match event.view() {
+                        gst::EventView::Caps(caps) => caps.get_caps_owned(),
+                        _ => return gst::PadProbeReturn::Ok,
+                    },
+                    _ => return gst::PadProbeReturn::Ok,
+                };
+
+                if let Ok(scaling) = shared_scaling.lock() {
+                    if let Err(e) = set_scaling_properties(
+                        &vbox,
+                        &scaling.mode,
+                        &scaling.bar_color,
+                        &caps,
+                        width,
+                        height,
+                    ) {
+                        tracing::warn!(
+                            input = input_name.as_str(),
+                            "Failed to configure scaling: {}",
+                            e
+                        );
+                    }
+                }
+                gst::PadProbeReturn::Ok
+            });
 
         let audio = audio_convert.clone();
         let video = video_convert.clone();
@@ -183,58 +318,64 @@ impl URI {
             }
         });
 
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .map_err(|e| mixer::Error::System(e.to_string()))?
-            .as_millis();
-
-        let recording_config = OutputConfig {
-            name: format!("record_{}", config.name),
-            audio: config.audio.clone(),
-            video: config.video.clone(),
-            encoder: EncoderConfig {
-                audio: AudioEncoderConfig {
-                    encoder: AudioEncoder::Vorbis,
-                },
-                video: VideoEncoderConfig {
-                    encoder: VideoEncoder::VP9,
-                    profile: None,
-                    preset: None,
-                    speed: None,
-                },
-            },
-            mux: Some(Mux::MKV),
-        };
-
-        let record_output = match config.record {
-            true => Some(FileOutput::create(
-                recording_config,
-                &format!("./recordings/input_{}_{}.mkv", config.name, timestamp),
-            )?),
+        // Cue the source to its in point as soon as it can be seeked.
+        let playback = config.playback.clone();
+        if playback != PlaybackConfig::default() {
+            let weak_source = source.downgrade();
+            source.connect_no_more_pads(move |_| {
+                let source = match weak_source.upgrade() {
+                    Some(source) => source,
+                    None => return,
+                };
+                let playback = playback.clone();
+                // Seeking from the streaming thread would deadlock.
+                std::thread::spawn(move || {
+                    if let Err(e) = super::seek_source(
+                        &source,
+                        gst::ClockTime::from_mseconds(playback.in_point_ms.unwrap_or(0)),
+                        playback.out_point_ms.map(gst::ClockTime::from_mseconds),
+                    ) {
+                        tracing::warn!("Failed to cue input: {}", e);
+                    }
+                });
+            });
+        }
 
-            false => None,
-        };
+        let snapshot = Snapshot::create(&format!("input_{}", config.name))?;
 
         Ok(Self {
             name: config.name.to_string(),
-            location: config.name.to_string(),
+            location: uri.to_string(),
             config,
             pipeline: None,
             source,
             audio_tee,
             audio_tee_queue,
             audio_convert,
+            audio_gain,
+            audio_equalizer,
+            audio_gate,
+            audio_compressor,
             audio_volume,
             audio_resample,
+            audio_format_convert,
+            audio_capsfilter,
             audio_queue,
             video_tee,
             video_tee_queue,
+            snapshot,
             video_convert,
+            video_crop,
+            video_flip,
+            video_balance,
             video_scale,
             video_rate,
+            video_alpha,
+            video_box,
             video_capsfilter,
             video_queue,
-            record_output,
+            scaling,
+            paused_at: None,
         })
     }
 
@@ -255,33 +396,43 @@ impl URI {
             &self.video_tee,
         ])?;
 
-        if let Some(record_output) = self.record_output.as_mut() {
-            record_output.link(
-                pipeline.clone(),
-                self.audio_tee.clone(),
-                self.video_tee.clone(),
-            )?;
-        }
-
         pipeline.add_many(&[
             &self.source,
             &self.audio_convert,
+            &self.audio_gain,
+            &self.audio_equalizer,
+            &self.audio_gate,
+            &self.audio_compressor,
             &self.audio_volume,
             &self.audio_resample,
+            &self.audio_format_convert,
+            &self.audio_capsfilter,
             &self.audio_queue,
             &self.video_convert,
+            &self.video_crop,
+            &self.video_flip,
+            &self.video_balance,
             &self.video_scale,
             &self.video_rate,
+            &self.video_alpha,
+            &self.video_box,
             &self.video_capsfilter,
             &self.video_queue,
         ])?;
 
+        self.snapshot.link(&pipeline, &self.video_tee)?;
         self.pipeline = Some(pipeline);
 
         gst::Element::link_many(&[
             &self.audio_convert,
+            &self.audio_gain,
+            &self.audio_equalizer,
+            &self.audio_gate,
+            &self.audio_compressor,
             &self.audio_volume,
             &self.audio_resample,
+            &self.audio_format_convert,
+            &self.audio_capsfilter,
             &self.audio_tee_queue,
             &self.audio_tee,
             &self.audio_queue,
@@ -289,8 +440,13 @@ impl URI {
         ])?;
         gst::Element::link_many(&[
             &self.video_convert,
+            &self.video_crop,
+            &self.video_flip,
+            &self.video_balance,
             &self.video_scale,
             &self.video_rate,
+            &self.video_alpha,
+            &self.video_box,
             &self.video_capsfilter,
             &self.video_tee_queue,
             &self.video_tee,
@@ -324,19 +480,31 @@ impl URI {
         super::release_request_pad(&self.video_queue)?;
 
         if let Some(pipeline) = self.pipeline.as_ref() {
+            self.snapshot.unlink(pipeline)?;
             pipeline.remove_many(&[
                 &self.source,
                 &self.audio_tee,
                 &self.audio_tee_queue,
                 &self.audio_convert,
+                &self.audio_gain,
+                &self.audio_equalizer,
+                &self.audio_gate,
+                &self.audio_compressor,
                 &self.audio_volume,
                 &self.audio_resample,
+                &self.audio_format_convert,
+                &self.audio_capsfilter,
                 &self.audio_queue,
                 &self.video_tee,
                 &self.video_tee_queue,
                 &self.video_convert,
+                &self.video_crop,
+                &self.video_flip,
+                &self.video_balance,
                 &self.video_scale,
                 &self.video_rate,
+                &self.video_alpha,
+                &self.video_box,
                 &self.video_capsfilter,
                 &self.video_queue,
             ])?;
@@ -349,16 +517,94 @@ impl URI {
         self.source.set_state(state)?;
         self.audio_convert.set_state(state)?;
         self.audio_resample.set_state(state)?;
+        self.audio_format_convert.set_state(state)?;
+        self.audio_capsfilter.set_state(state)?;
+        self.audio_gain.set_state(state)?;
+        self.audio_equalizer.set_state(state)?;
+        self.audio_gate.set_state(state)?;
+        self.audio_compressor.set_state(state)?;
         self.audio_volume.set_state(state)?;
         self.audio_queue.set_state(state)?;
         self.video_convert.set_state(state)?;
+        self.video_crop.set_state(state)?;
+        self.video_flip.set_state(state)?;
+        self.video_balance.set_state(state)?;
         self.video_scale.set_state(state)?;
         self.video_rate.set_state(state)?;
+        self.video_alpha.set_state(state)?;
+        self.video_box.set_state(state)?;
         self.video_capsfilter.set_state(state)?;
         self.video_queue.set_state(state)?;
+        self.snapshot.set_state(state)?;
         Ok(())
     }
 
+    /// Resumes playback after `pause`, shifting the source by the time spent paused.
+    pub fn play(&mut self) -> Result<()> {
+        if let Some(paused_at) = self.paused_at.take() {
+            let paused_for = gst::format::GenericFormattedValue::Time(
+                self.source.get_current_running_time() - paused_at,
+            )
+            .get_value();
+            for pad in self.source.get_src_pads() {
+                pad.set_offset(pad.get_offset() + paused_for);
+            }
+        }
+
+        self.source.sync_state_with_parent()?;
+        Ok(())
+    }
+
+    pub fn pause(&mut self) -> Result<()> {
+        if self.paused_at.is_none() {
+            self.source.set_state(gst::State::Paused)?;
+            self.paused_at = Some(self.source.get_current_running_time());
+        }
+        Ok(())
+    }
+
+    /// Seeks to `position_ms`, still stopping at the out point.
+    pub fn seek(&mut self, position_ms: u64) -> Result<()> {
+        super::seek_source(
+            &self.source,
+            gst::ClockTime::from_mseconds(position_ms),
+            self.config
+                .playback
+                .out_point_ms
+                .map(gst::ClockTime::from_mseconds),
+        )?;
+
+        // The seek moved the offset to the current running time, which is where a paused
+        // source resumes from.
+        if self.paused_at.is_some() {
+            self.paused_at = Some(self.source.get_current_running_time());
+        }
+        Ok(())
+    }
+
+    /// Seeks back to the in point, or the start of the file.
+    pub fn restart(&mut self) -> Result<()> {
+        self.seek(self.config.playback.in_point_ms.unwrap_or(0))
+    }
+
+    pub fn playback(&self) -> PlaybackStatus {
+        let pad = self.source.get_src_pads().into_iter().next();
+
+        PlaybackStatus {
+            paused: self.paused_at.is_some(),
+            position_ms: pad
+                .as_ref()
+                .and_then(|pad| pad.query_position::<gst::ClockTime>())
+                .and_then(|position| position.mseconds()),
+            duration_ms: pad
+                .as_ref()
+                .and_then(|pad| pad.query_duration::<gst::ClockTime>())
+                .and_then(|duration| duration.mseconds()),
+            in_point_ms: self.config.playback.in_point_ms,
+            out_point_ms: self.config.playback.out_point_ms,
+        }
+    }
+
     pub fn set_volume(&mut self, volume: f64, update_config: bool) -> Result<()> {
         if update_config {
             self.config.audio.volume = volume;
@@ -367,6 +613,75 @@ impl URI {
         Ok(())
     }
 
+    pub fn set_mute(&mut self, mute: bool, update_config: bool) -> Result<()> {
+        if update_config {
+            self.config.audio.mute = mute;
+        }
+        self.audio_volume.set_property("mute", &mute)?;
+        Ok(())
+    }
+
+    pub fn set_gain(&mut self, gain: f64, update_config: bool) -> Result<()> {
+        if update_config {
+            self.config.audio.gain = gain;
+        }
+        self.audio_gain
+            .set_property("volume", &super::gain_to_volume(gain))?;
+        Ok(())
+    }
+
+    pub fn set_equalizer(&mut self, equalizer: EqualizerConfig, update_config: bool) -> Result<()> {
+        super::set_equalizer_properties(&self.audio_equalizer, &equalizer)?;
+        if update_config {
+            self.config.audio.equalizer = equalizer;
+        }
+        Ok(())
+    }
+
+    pub fn set_compressor(
+        &mut self,
+        compressor: Option<CompressorConfig>,
+        update_config: bool,
+    ) -> Result<()> {
+        super::set_compressor_properties(&self.audio_compressor, compressor.as_ref())?;
+        if update_config {
+            self.config.audio.compressor = compressor;
+        }
+        Ok(())
+    }
+
+    pub fn set_noise_gate(
+        &mut self,
+        noise_gate: Option<NoiseGateConfig>,
+        update_config: bool,
+    ) -> Result<()> {
+        super::set_noise_gate_properties(&self.audio_gate, noise_gate.as_ref())?;
+        if update_config {
+            self.config.audio.noise_gate = noise_gate;
+        }
+        Ok(())
+    }
+
+    pub fn set_av_offset(&mut self, av_offset_ms: i64, update_config: bool) -> Result<()> {
+        super::set_av_offset_properties(&self.audio_convert, &self.video_convert, av_offset_ms)?;
+        if update_config {
+            self.config.audio.av_offset_ms = av_offset_ms;
+        }
+        Ok(())
+    }
+
+    pub fn set_audio_mode(&mut self, mode: AudioMode, update_config: bool) -> Result<()> {
+        if update_config {
+            self.config.audio.mode = mode;
+        }
+        Ok(())
+    }
+
+    pub fn set_audio_format(&mut self, audio: &MixerAudioConfig) -> Result<()> {
+        self.audio_capsfilter.set_property("caps", &audio.caps())?;
+        Ok(())
+    }
+
     pub fn set_zorder(&mut self, zorder: u32, update_config: bool) -> Result<()> {
         if update_config {
             self.config.video.zorder = Some(zorder);
@@ -463,7 +778,257 @@ impl URI {
         Ok(())
     }
 
+    pub fn set_crop(&mut self, crop: CropConfig, update_config: bool) -> Result<()> {
+        super::set_crop_properties(&self.video_crop, &crop)?;
+        self.update_scaling(
+            &self.config.video.scaling_mode,
+            &self.config.video.bar_color,
+        )?;
+        if update_config {
+            self.config.video.crop = crop;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_flip(&mut self, flip: Option<VideoFlip>, update_config: bool) -> Result<()> {
+        super::set_flip_properties(
+            &self.video_flip,
+            flip.as_ref(),
+            self.config.video.rotation.as_ref(),
+        );
+        if update_config {
+            self.config.video.flip = flip;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_rotation(
+        &mut self,
+        rotation: Option<VideoRotation>,
+        update_config: bool,
+    ) -> Result<()> {
+        super::set_flip_properties(
+            &self.video_flip,
+            self.config.video.flip.as_ref(),
+            rotation.as_ref(),
+        );
+        self.update_scaling(
+            &self.config.video.scaling_mode,
+            &self.config.video.bar_color,
+        )?;
+        if update_config {
+            self.config.video.rotation = rotation;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_color(&mut self, color: ColorConfig, update_config: bool) -> Result<()> {
+        super::set_color_properties(&self.video_balance, &color)?;
+        if update_config {
+            self.config.video.color = color;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_chroma_key(
+        &mut self,
+        chroma_key: Option<ChromaKeyConfig>,
+        update_config: bool,
+    ) -> Result<()> {
+        set_chroma_key_properties(&self.video_alpha, chroma_key.as_ref())?;
+        if update_config {
+            self.config.video.chroma_key = chroma_key;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_scaling_mode(
+        &mut self,
+        scaling_mode: ScalingMode,
+        update_config: bool,
+    ) -> Result<()> {
+        self.update_scaling(&scaling_mode, &self.config.video.bar_color)?;
+        if update_config {
+            self.config.video.scaling_mode = scaling_mode;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_bar_color(&mut self, bar_color: BarColor, update_config: bool) -> Result<()> {
+        self.update_scaling(&self.config.video.scaling_mode, &bar_color)?;
+        if update_config {
+            self.config.video.bar_color = bar_color;
+        }
+
+        Ok(())
+    }
+
+    pub fn video_pad(&self) -> Option<gst::Pad> {
+        self.video_queue
+            .get_static_pad("src")
+            .and_then(|pad| pad.get_peer())
+    }
+
+    pub fn video_tee(&self) -> gst::Element {
+        self.video_tee.clone()
+    }
+
+    pub fn audio_tee(&self) -> gst::Element {
+        self.audio_tee.clone()
+    }
+
+    /// Returns the latest frame of the input, to be encoded with
+    /// [`snapshot::encode`](../../snapshot/fn.encode.html).
+    pub fn snapshot(&self) -> Result<gst::Sample> {
+        self.snapshot.latest()
+    }
+
+    pub fn audio_pad(&self) -> Option<gst::Pad> {
+        self.audio_queue
+            .get_static_pad("src")
+            .and_then(|pad| pad.get_peer())
+    }
+
     pub fn config(&self) -> Config {
         self.config.clone()
     }
+
+    /// Applies the scaling to the video as it is currently cropped and rotated. A crop or
+    /// rotation only takes effect once the caps are renegotiated, the probe on the video flip
+    /// applies the scaling again when they are.
+    fn update_scaling(&self, scaling_mode: &ScalingMode, bar_color: &BarColor) -> Result<()> {
+        {
+            let mut scaling = self.scaling.lock().map_err(|_| mixer::Error::Unknown)?;
+            scaling.mode = scaling_mode.clone();
+            scaling.bar_color = bar_color.clone();
+        }
+
+        // Until uridecodebin exposes its video pad the source size is unknown, the probe on the
+        // video flip configures the scaling once it is.
+        let source_caps = match self
+            .video_flip
+            .get_static_pad("src")
+            .and_then(|pad| pad.get_current_caps())
+        {
+            Some(caps) => caps,
+            None => return Ok(()),
+        };
+
+        let caps = self
+            .video_capsfilter
+            .get_property("caps")?
+            .get::<gst::Caps>()
+            .map_err(|_| mixer::Error::Gstreamer("Failed to get caps of capsfilter".to_string()))?
+            .ok_or_else(|| mixer::Error::Gstreamer("capsfilter has no caps".to_string()))?;
+        let structure = caps.get_structure(0).ok_or_else(|| {
+            mixer::Error::Gstreamer("Failed to get structure of capsfilter caps".to_string())
+        })?;
+        let width = structure
+            .get_some::<i32>("width")
+            .map_err(|_| mixer::Error::Gstreamer("capsfilter caps have no width".to_string()))?;
+        let height = structure
+            .get_some::<i32>("height")
+            .map_err(|_| mixer::Error::Gstreamer("capsfilter caps have no height".to_string()))?;
+
+        set_scaling_properties(
+            &self.video_box,
+            scaling_mode,
+            bar_color,
+            &source_caps,
+            width,
+            height,
+        )
+    }
+}
+
+/// Configures the `videobox` element so that video with the given source caps ends up filling
+/// `width`x`height` according to the scaling mode. The source is scaled by videoscale to the size
+/// the box expects upstream, the box then crops what overflows or adds bars around it.
+fn set_scaling_properties(
+    video_box: &gst::Element,
+    scaling_mode: &ScalingMode,
+    bar_color: &BarColor,
+    source_caps: &gst::Caps,
+    width: i32,
+    height: i32,
+) -> Result<()> {
+    let structure = source_caps.get_structure(0).ok_or_else(|| {
+        mixer::Error::Gstreamer("Failed to get structure of source caps".to_string())
+    })?;
+    let source_width = structure
+        .get_some::<i32>("width")
+        .map_err(|_| mixer::Error::Gstreamer("source caps have no width".to_string()))?;
+    let source_height = structure
+        .get_some::<i32>("height")
+        .map_err(|_| mixer::Error::Gstreamer("source caps have no height".to_string()))?;
+    let par = structure
+        .get_some::<gst::Fraction>("pixel-aspect-ratio")
+        .unwrap_or_else(|_| gst::Fraction::new(1, 1));
+
+    let source_aspect = f64::from(source_width) * f64::from(*par.numer())
+        / (f64::from(source_height) * f64::from(*par.denom()));
+    let target_aspect = f64::from(width) / f64::from(height);
+    let fit_width = (f64::from(height) * source_aspect).round() as i32;
+    let fit_height = (f64::from(width) / source_aspect).round() as i32;
+
+    let (scaled_width, scaled_height) = match scaling_mode {
+        ScalingMode::Stretch => (width, height),
+        ScalingMode::Fit if source_aspect > target_aspect => (width, fit_height),
+        ScalingMode::Fit => (fit_width, height),
+        ScalingMode::Fill if source_aspect > target_aspect => (fit_width, height),
+        ScalingMode::Fill => (width, fit_height),
+    };
+
+    // videobox crops for positive values and adds borders for negative ones.
+    let horizontal = scaled_width - width;
+    let vertical = scaled_height - height;
+    video_box.set_property("left", &(horizontal / 2))?;
+    video_box.set_property("right", &(horizontal - horizontal / 2))?;
+    video_box.set_property("top", &(vertical / 2))?;
+    video_box.set_property("bottom", &(vertical - vertical / 2))?;
+
+    let (fill, border_alpha) = match bar_color {
+        BarColor::Black => ("black", 1.0),
+        BarColor::White => ("white", 1.0),
+        BarColor::Red => ("red", 1.0),
+        BarColor::Green => ("green", 1.0),
+        BarColor::Blue => ("blue", 1.0),
+        BarColor::Yellow => ("yellow", 1.0),
+        BarColor::Transparent => ("black", 0.0),
+    };
+    video_box.set_property_from_str("fill", fill);
+    video_box.set_property("border-alpha", &border_alpha)?;
+
+    Ok(())
+}
+
+/// Configures the `alpha` element for the given chroma key. When no chroma key is supplied the
+/// element is left in place with a fully opaque alpha, so it can be enabled again later without
+/// relinking the input.
+fn set_chroma_key_properties(
+    alpha: &gst::Element,
+    chroma_key: Option<&ChromaKeyConfig>,
+) -> Result<()> {
+    match chroma_key {
+        Some(chroma_key) => {
+            alpha.set_property_from_str("method", &chroma_key.color.to_string());
+            alpha.set_property("target-r", &chroma_key.target_r)?;
+            alpha.set_property("target-g", &chroma_key.target_g)?;
+            alpha.set_property("target-b", &chroma_key.target_b)?;
+            alpha.set_property("angle", &chroma_key.angle)?;
+            alpha.set_property("noise-level", &chroma_key.noise_level)?;
+        }
+        None => {
+            alpha.set_property_from_str("method", "set");
+            alpha.set_property("alpha", &1.0f64)?;
+        }
+    }
+
+    Ok(())
 }
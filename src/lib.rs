@@ -1,8 +1,11 @@
 //#![warn(clippy::pedantic)]
+#![recursion_limit = "256"]
 pub mod http;
 pub mod input;
 pub mod mixer;
 pub mod output;
+pub mod secret;
+pub mod snapshot;
 
 extern crate gstreamer as gst;
 extern crate gstreamer_video as gst_video;
@@ -34,6 +37,20 @@ pub struct VideoConfig {
     #[serde(default = "VideoConfig::alpha_default")]
     pub alpha: f64,
     pub repeat: bool,
+    #[serde(default)]
+    pub chroma_key: Option<ChromaKeyConfig>,
+    #[serde(default)]
+    pub crop: CropConfig,
+    #[serde(default)]
+    pub flip: Option<VideoFlip>,
+    #[serde(default)]
+    pub rotation: Option<VideoRotation>,
+    #[serde(default = "VideoConfig::scaling_mode_default")]
+    pub scaling_mode: ScalingMode,
+    #[serde(default = "VideoConfig::bar_color_default")]
+    pub bar_color: BarColor,
+    #[serde(default)]
+    pub color: ColorConfig,
 }
 
 impl VideoConfig {
@@ -56,6 +73,14 @@ impl VideoConfig {
     fn alpha_default() -> f64 {
         1.0
     }
+
+    fn scaling_mode_default() -> ScalingMode {
+        ScalingMode::Stretch
+    }
+
+    fn bar_color_default() -> BarColor {
+        BarColor::Black
+    }
 }
 
 impl Default for VideoConfig {
@@ -70,10 +95,148 @@ impl Default for VideoConfig {
             alpha: Self::alpha_default(),
             repeat: false,
             format: Self::format_default(),
+            chroma_key: None,
+            crop: CropConfig::default(),
+            flip: None,
+            rotation: None,
+            scaling_mode: Self::scaling_mode_default(),
+            bar_color: Self::bar_color_default(),
+            color: ColorConfig::default(),
+        }
+    }
+}
+
+/// Color correction applied to an input's video through `videobalance`.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ColorConfig {
+    /// Between -1.0 and 1.0
+    pub brightness: f64,
+    /// Between 0.0 and 2.0
+    #[serde(default = "ColorConfig::contrast_default")]
+    pub contrast: f64,
+    /// Between 0.0 and 2.0
+    #[serde(default = "ColorConfig::saturation_default")]
+    pub saturation: f64,
+    /// Between -1.0 and 1.0
+    pub hue: f64,
+}
+
+impl ColorConfig {
+    fn contrast_default() -> f64 {
+        1.0
+    }
+
+    fn saturation_default() -> f64 {
+        1.0
+    }
+}
+
+impl Default for ColorConfig {
+    fn default() -> Self {
+        Self {
+            brightness: 0.0,
+            contrast: Self::contrast_default(),
+            saturation: Self::saturation_default(),
+            hue: 0.0,
+        }
+    }
+}
+
+/// Number of pixels removed from each edge of an input's video by `videocrop`.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct CropConfig {
+    pub top: i32,
+    pub bottom: i32,
+    pub left: i32,
+    pub right: i32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Clone)]
+pub enum VideoFlip {
+    Horizontal,
+    Vertical,
+}
+
+/// How an input's video is fit into its width and height when the aspect ratios differ.
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Clone)]
+pub enum ScalingMode {
+    /// Scale to the exact size, distorting the picture.
+    Stretch,
+    /// Scale until the picture fits, adding letterbox or pillarbox bars.
+    Fit,
+    /// Scale until the picture covers the whole area, cropping what overflows.
+    Fill,
+}
+
+/// Color of the bars added by `ScalingMode::Fit`.
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Clone)]
+pub enum BarColor {
+    Black,
+    White,
+    Red,
+    Green,
+    Blue,
+    Yellow,
+    Transparent,
+}
+
+/// Clockwise rotation applied to an input's video.
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Clone)]
+pub enum VideoRotation {
+    Rotate90,
+    Rotate180,
+    Rotate270,
+}
+
+/// Chroma key settings applied to an input's video through the `alpha` element.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ChromaKeyConfig {
+    pub color: ChromaKeyColor,
+    /// Target color, only used when `color` is `Custom`.
+    pub target_r: u32,
+    pub target_g: u32,
+    pub target_b: u32,
+    pub angle: f32,
+    pub noise_level: f32,
+}
+
+impl Default for ChromaKeyConfig {
+    fn default() -> Self {
+        Self {
+            color: ChromaKeyColor::Green,
+            target_r: 0,
+            target_g: 255,
+            target_b: 0,
+            angle: 20.0,
+            noise_level: 2.0,
         }
     }
 }
 
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Clone)]
+pub enum ChromaKeyColor {
+    Green,
+    Blue,
+    Custom,
+}
+
+impl std::fmt::Display for ChromaKeyColor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use ChromaKeyColor::*;
+
+        let s = match self {
+            Green => "green",
+            Blue => "blue",
+            Custom => "custom",
+        };
+
+        f.write_str(s)
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct VideoEncoderConfig {
     #[serde(default = "VideoEncoderConfig::encoder_default")]
@@ -125,12 +288,31 @@ impl Default for AudioEncoderConfig {
 pub struct AudioConfig {
     #[serde(default = "AudioConfig::volume_default")]
     pub volume: f64,
+    #[serde(default = "AudioConfig::mode_default")]
+    pub mode: AudioMode,
+    /// Silences the audio without changing the volume.
+    pub mute: bool,
+    /// Input gain in dB, up to +20dB.
+    pub gain: f64,
+    pub equalizer: EqualizerConfig,
+    pub compressor: Option<CompressorConfig>,
+    pub noise_gate: Option<NoiseGateConfig>,
+    /// Delay in milliseconds applied to the audio relative to the video, up to 10 seconds.
+    /// Negative values delay the video instead.
+    pub av_offset_ms: i64,
 }
 
 impl Default for AudioConfig {
     fn default() -> Self {
         Self {
             volume: Self::volume_default(),
+            mode: Self::mode_default(),
+            mute: false,
+            gain: 0.0,
+            equalizer: EqualizerConfig::default(),
+            compressor: None,
+            noise_gate: None,
+            av_offset_ms: 0,
         }
     }
 }
@@ -139,6 +321,341 @@ impl AudioConfig {
     fn volume_default() -> f64 {
         1.0
     }
+
+    fn mode_default() -> AudioMode {
+        AudioMode::FollowVideo
+    }
+}
+
+/// Program audio settings of a mixer. Inputs are converted to the format of the mixer they are
+/// added to.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct MixerAudioConfig {
+    pub volume: f64,
+    /// Loudness measurement and normalization of the program audio.
+    pub loudness: Option<LoudnessConfig>,
+    /// Sample rate in Hz of the program audio. Inputs are resampled to the rate of their mixer.
+    pub sample_rate: i32,
+    /// Channel count of the program audio. Inputs are down or up-mixed to the channels of their
+    /// mixer.
+    pub channels: i32,
+    /// Sample format of the program audio.
+    pub format: AudioFormat,
+}
+
+impl Default for MixerAudioConfig {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            loudness: None,
+            sample_rate: 48000,
+            channels: 2,
+            format: AudioFormat::S32LE,
+        }
+    }
+}
+
+impl MixerAudioConfig {
+    /// Returns the raw audio caps described by the sample rate, channels and format.
+    pub fn caps(&self) -> gst::Caps {
+        let mut caps = gst::Caps::builder("audio/x-raw")
+            .field("rate", &self.sample_rate)
+            .field("channels", &self.channels)
+            .field("layout", &"interleaved")
+            .field("format", &self.format.to_string());
+
+        // More than two channels are only mixed deliberately when their positions are known,
+        // so use the common layouts up to 7.1.
+        let mask: Option<u64> = match self.channels {
+            3 => Some(0xb),
+            4 => Some(0x33),
+            5 => Some(0x37),
+            6 => Some(0x3f),
+            7 => Some(0x13f),
+            8 => Some(0xc3f),
+            _ => None,
+        };
+        if let Some(mask) = mask {
+            caps = caps.field("channel-mask", &gst::Bitmask::new(mask));
+        }
+
+        caps.build()
+    }
+}
+
+/// Gain in dB of each band of `equalizer-3bands`, between -24dB and +12dB.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct EqualizerConfig {
+    pub low: f64,
+    pub mid: f64,
+    pub high: f64,
+}
+
+/// Compressor settings applied through `audiodynamic`. A high ratio turns it into a limiter.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct CompressorConfig {
+    /// Amplitude above which the audio is compressed, between 0.0 and 1.0
+    #[serde(default = "CompressorConfig::threshold_default")]
+    pub threshold: f32,
+    #[serde(default = "CompressorConfig::ratio_default")]
+    pub ratio: f32,
+}
+
+impl CompressorConfig {
+    fn threshold_default() -> f32 {
+        0.5
+    }
+
+    fn ratio_default() -> f32 {
+        4.0
+    }
+}
+
+impl Default for CompressorConfig {
+    fn default() -> Self {
+        Self {
+            threshold: Self::threshold_default(),
+            ratio: Self::ratio_default(),
+        }
+    }
+}
+
+/// Noise gate settings applied through `audiodynamic` in expander mode.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct NoiseGateConfig {
+    /// Amplitude below which the audio is attenuated, between 0.0 and 1.0
+    #[serde(default = "NoiseGateConfig::threshold_default")]
+    pub threshold: f32,
+    #[serde(default = "NoiseGateConfig::ratio_default")]
+    pub ratio: f32,
+}
+
+impl NoiseGateConfig {
+    fn threshold_default() -> f32 {
+        0.05
+    }
+
+    fn ratio_default() -> f32 {
+        10.0
+    }
+}
+
+impl Default for NoiseGateConfig {
+    fn default() -> Self {
+        Self {
+            threshold: Self::threshold_default(),
+            ratio: Self::ratio_default(),
+        }
+    }
+}
+
+/// In and out points of file inputs, in milliseconds from the start of the file.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct PlaybackConfig {
+    pub in_point_ms: Option<u64>,
+    pub out_point_ms: Option<u64>,
+}
+
+/// Where the isolated (ISO) recordings of the inputs of a mixer are written.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct IsoConfig {
+    /// Directory of the recordings and session manifests, created when missing.
+    #[serde(default = "IsoConfig::directory_default")]
+    pub directory: String,
+    /// File name of each recording, `{mixer}`, `{input}` and `{session}` are replaced by the mixer
+    /// name, the input name and the start time of the session.
+    #[serde(default = "IsoConfig::template_default")]
+    pub template: String,
+}
+
+impl IsoConfig {
+    fn directory_default() -> String {
+        "./recordings".to_string()
+    }
+
+    fn template_default() -> String {
+        "{mixer}_{input}_{session}.mkv".to_string()
+    }
+}
+
+impl Default for IsoConfig {
+    fn default() -> Self {
+        Self {
+            directory: Self::directory_default(),
+            template: Self::template_default(),
+        }
+    }
+}
+
+/// Splitting of file recordings into segments. A new segment is started at the first keyframe after
+/// either limit is reached.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct SegmentConfig {
+    /// Maximum duration of a segment in seconds, 0 for no limit.
+    pub max_duration_s: u64,
+    /// Maximum size of a segment in bytes, 0 for no limit.
+    pub max_size_bytes: u64,
+}
+
+/// Settings of multiviewer outputs.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct MultiviewerConfig {
+    /// Output type the multiviewer is sent to, using the location of the multiviewer.
+    #[serde(default = "MultiviewerConfig::sink_type_default")]
+    pub sink_type: String,
+    /// Width of the tally border around each tile in pixels.
+    #[serde(default = "MultiviewerConfig::border_default")]
+    pub border: i32,
+}
+
+impl MultiviewerConfig {
+    fn sink_type_default() -> String {
+        "Auto".to_string()
+    }
+
+    fn border_default() -> i32 {
+        4
+    }
+}
+
+impl Default for MultiviewerConfig {
+    fn default() -> Self {
+        Self {
+            sink_type: Self::sink_type_default(),
+            border: Self::border_default(),
+        }
+    }
+}
+
+/// Rolling buffer settings of replay inputs.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ReplayConfig {
+    /// Seconds of video kept in the buffer, at most
+    /// [`MAX_DURATION_S`](input/replay/constant.MAX_DURATION_S.html).
+    #[serde(default = "ReplayConfig::duration_s_default")]
+    pub duration_s: u64,
+    /// Size frames are stored at, replays are scaled back up to the size of the input.
+    #[serde(default = "ReplayConfig::width_default")]
+    pub width: i32,
+    #[serde(default = "ReplayConfig::height_default")]
+    pub height: i32,
+    /// JPEG quality of the stored frames.
+    #[serde(default = "ReplayConfig::quality_default")]
+    pub quality: i32,
+}
+
+impl ReplayConfig {
+    fn duration_s_default() -> u64 {
+        30
+    }
+
+    fn width_default() -> i32 {
+        1280
+    }
+
+    fn height_default() -> i32 {
+        720
+    }
+
+    fn quality_default() -> i32 {
+        85
+    }
+}
+
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        Self {
+            duration_s: Self::duration_s_default(),
+            width: Self::width_default(),
+            height: Self::height_default(),
+            quality: Self::quality_default(),
+        }
+    }
+}
+
+/// EBU R128 loudness settings for the program audio of a mixer.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct LoudnessConfig {
+    /// Normalizes the loudness to the target, when disabled the loudness is only measured.
+    #[serde(default = "LoudnessConfig::normalize_default")]
+    pub normalize: bool,
+    /// Integrated loudness target in LUFS.
+    #[serde(default = "LoudnessConfig::target_default")]
+    pub target: f64,
+    /// Maximum true peak in dBTP, enforced by the limiter of the normalizer.
+    #[serde(default = "LoudnessConfig::max_true_peak_default")]
+    pub max_true_peak: f64,
+}
+
+impl LoudnessConfig {
+    fn normalize_default() -> bool {
+        true
+    }
+
+    fn target_default() -> f64 {
+        -23.0
+    }
+
+    fn max_true_peak_default() -> f64 {
+        -1.0
+    }
+}
+
+impl Default for LoudnessConfig {
+    fn default() -> Self {
+        Self {
+            normalize: Self::normalize_default(),
+            target: Self::target_default(),
+            max_true_peak: Self::max_true_peak_default(),
+        }
+    }
+}
+
+/// Sample format of raw audio.
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Clone)]
+pub enum AudioFormat {
+    S16LE,
+    S24LE,
+    S32LE,
+    F32LE,
+    F64LE,
+}
+
+impl std::fmt::Display for AudioFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use AudioFormat::*;
+
+        let s = match self {
+            S16LE => "S16LE",
+            S24LE => "S24LE",
+            S32LE => "S32LE",
+            F32LE => "F32LE",
+            F64LE => "F64LE",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+/// Determines when an input's audio is heard on the program.
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Clone)]
+pub enum AudioMode {
+    /// Audible only while the input is the program audio source.
+    FollowVideo,
+    /// Always audible, regardless of the active input. (e.g. a host microphone)
+    AlwaysOn,
+    /// Never audible.
+    Off,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Clone)]
@@ -255,6 +772,7 @@ pub enum AudioEncoder {
     AAC,
     MP3,
     Vorbis,
+    Opus,
 }
 
 impl std::fmt::Display for AudioEncoder {
@@ -265,6 +783,7 @@ impl std::fmt::Display for AudioEncoder {
             AAC => "fdkaacenc",
             MP3 => "lamemp3enc",
             Vorbis => "vorbisenc",
+            Opus => "opusenc",
         };
 
         f.write_str(s)
@@ -395,6 +914,58 @@ pub enum Format {
     GRAY8,
 }
 
+impl Format {
+    /// Returns whether the format carries a per-pixel alpha channel.
+    pub fn has_alpha(&self) -> bool {
+        use Format::*;
+
+        matches!(
+            self,
+            AYUV64
+                | ARGB64
+                | GBRA_12LE
+                | GBRA_12BE
+                | Y412_LE
+                | Y412_BE
+                | A444_10LE
+                | GBRA_10LE
+                | A444_10BE
+                | GBRA_10BE
+                | A422_10LE
+                | A422_10BE
+                | A420_10LE
+                | A420_10BE
+                | RGB10A2_LE
+                | BGR10A2_LE
+                | Y410
+                | GBRA
+                | ABGR
+                | VUYA
+                | BGRA
+                | AYUV
+                | ARGB
+                | RGBA
+                | A420
+        )
+    }
+
+    /// Returns a format able to carry alpha that is closest to this one. YUV formats map to
+    /// `AYUV` and RGB formats map to `BGRA`, formats which already carry alpha are returned as is.
+    pub fn with_alpha(&self) -> Format {
+        use Format::*;
+
+        if self.has_alpha() {
+            return self.clone();
+        }
+
+        match self {
+            GBR_12LE | GBR_12BE | GBR_10LE | GBR_10BE | r210 | GBR | xBGR | BGRx | xRGB | RGBx
+            | BGR | RGB | RGB16 | BGR16 | RGB15 | BGR15 | RGB8P => BGRA,
+            _ => AYUV,
+        }
+    }
+}
+
 impl std::fmt::Display for Format {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         use Format::*;
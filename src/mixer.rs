@@ -1,20 +1,124 @@
 mod error;
+pub mod iso;
 
-use crate::{gst_create_element, input, output, AudioConfig, Result, VideoConfig};
+use crate::{
+    gst_create_element, input, output, snapshot::Snapshot, AudioConfig, AudioMode, IsoConfig,
+    LoudnessConfig, MixerAudioConfig, PlaybackConfig, Result, VideoConfig,
+};
 pub use error::Error;
 use gst::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// Number of events a mixer keeps around for clients to poll.
+const MAX_EVENTS: usize = 100;
+
+/// How long removing a recording output waits for its file to be finalized.
+const FINALIZE_TIMEOUT_S: u64 = 5;
+
+/// Lowest loudness reported, in LUFS or dBTP, standing in for the `-inf` of silence, which JSON
+/// can't represent. It is the absolute gate of EBU R128.
+const MIN_LOUDNESS: f64 = -70.0;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Config {
     pub name: String,
     pub video: VideoConfig,
-    pub audio: AudioConfig,
+    pub audio: MixerAudioConfig,
+}
+
+/// Routing of input audio onto the program audio bus.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct AudioBus {
+    /// Pairs the video of an input (key) with the audio of another input (value). When the key
+    /// is the active input the paired input is used as the program audio source.
+    pub pairings: HashMap<String, String>,
+}
+
+/// Most recent EBU R128 readings of the program audio.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Loudness {
+    /// Loudness of the last 400ms in LUFS.
+    pub momentary: f64,
+    /// Loudness of the last 3s in LUFS.
+    pub short_term: f64,
+    /// Loudness since the mixer started in LUFS.
+    pub integrated: f64,
+    /// Loudness range in LU.
+    pub range: f64,
+    /// Highest true peak of all channels in dBTP.
+    pub true_peak: f64,
+}
+
+impl Loudness {
+    /// Parses the message posted by `ebur128level`.
+    fn from_structure(structure: &gst::StructureRef) -> Option<Self> {
+        let true_peak = structure
+            .get_some::<gst::Array>("true-peak")
+            .ok()?
+            .as_slice()
+            .iter()
+            .filter_map(|peak| peak.get_some::<f64>().ok())
+            .fold(0.0, f64::max);
+
+        Some(Self {
+            momentary: structure
+                .get_some::<f64>("momentary-loudness")
+                .ok()?
+                .max(MIN_LOUDNESS),
+            short_term: structure
+                .get_some::<f64>("shortterm-loudness")
+                .ok()?
+                .max(MIN_LOUDNESS),
+            integrated: structure
+                .get_some::<f64>("global-loudness")
+                .ok()?
+                .max(MIN_LOUDNESS),
+            range: structure.get_some::<f64>("loudness-range").ok()?.max(0.0),
+            true_peak: (20.0 * true_peak.log10()).max(MIN_LOUDNESS),
+        })
+    }
+}
+
+/// Whether an input can be seen in the program.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum Tally {
+    /// The input is the active input.
+    OnAir,
+    /// The input is not active but is visible in the program.
+    Visible,
+    Off,
+}
+
+/// Something that happened in a mixer which clients may want to react to.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(tag = "type")]
+pub enum Event {
+    /// The last item of a playlist input has ended.
+    PlaylistFinished {
+        input: String,
+        /// Milliseconds since the unix epoch.
+        timestamp: u64,
+    },
+    /// The tally of an input has changed.
+    TallyChanged {
+        input: String,
+        tally: Tally,
+        /// Milliseconds since the unix epoch.
+        timestamp: u64,
+    },
 }
 
 pub struct Mixer {
     config: Config,
+    active_input: Option<String>,
+    audio_bus: AudioBus,
     pipeline: gst::Pipeline,
     audio_mixer: gst::Element,
     video_mixer: gst::Element,
@@ -22,6 +126,13 @@ pub struct Mixer {
     pub outputs: HashMap<String, output::Output>,
     audio_out: gst::Element,
     video_out: gst::Element,
+    loudness: Arc<Mutex<Option<Loudness>>>,
+    events: Arc<Mutex<VecDeque<Event>>>,
+    tally: HashMap<String, Tally>,
+    iso: Option<iso::Session>,
+    snapshot: Snapshot,
+    /// Set once the end of stream sent by [`send_eos`](#method.send_eos) reached every output.
+    eos: Arc<AtomicBool>,
     join_handle: Option<std::thread::JoinHandle<()>>,
 }
 
@@ -45,6 +156,22 @@ impl Mixer {
             "compositor",
             format!("mixer_{}_video_compositor", config.name).as_str(),
         )?;
+
+        // The compositor blends in a format carrying alpha, so that inputs using a chroma key keep
+        // their per-pixel alpha. The result is converted to the configured format afterwards.
+        let video_blend_capsfilter = gst_create_element(
+            "capsfilter",
+            format!("mixer_{}_video_blend_capsfilter", config.name).as_str(),
+        )?;
+        let video_blend_caps = gst::Caps::builder("video/x-raw")
+            .field("format", &config.video.format.with_alpha().to_string())
+            .build();
+        video_blend_capsfilter.set_property("caps", &video_blend_caps)?;
+        let video_convert = gst_create_element(
+            "videoconvert",
+            format!("mixer_{}_video_convert", config.name).as_str(),
+        )?;
+
         let video_caps = gst::Caps::builder("video/x-raw")
             .field("framerate", &gst::Fraction::new(config.video.framerate, 1))
             .field("format", &config.video.format.to_string())
@@ -62,8 +189,25 @@ impl Mixer {
             gst_create_element("tee", format!("mixer_{}_video_tee", config.name).as_str())?;
         video_tee.set_property("allow-not-linked", &true)?;
 
-        pipeline.add_many(&[&video_mixer, &video_capsfilter, &video_queue, &video_tee])?;
-        gst::Element::link_many(&[&video_mixer, &video_capsfilter, &video_queue, &video_tee])?;
+        pipeline.add_many(&[
+            &video_mixer,
+            &video_blend_capsfilter,
+            &video_convert,
+            &video_capsfilter,
+            &video_queue,
+            &video_tee,
+        ])?;
+        gst::Element::link_many(&[
+            &video_mixer,
+            &video_blend_capsfilter,
+            &video_convert,
+            &video_capsfilter,
+            &video_queue,
+            &video_tee,
+        ])?;
+
+        let snapshot = Snapshot::create(&format!("mixer_{}", config.name))?;
+        snapshot.link(&pipeline, &video_tee)?;
 
         let audio_mixer = gst_create_element(
             "audiomixer",
@@ -78,22 +222,27 @@ impl Mixer {
             "capsfilter",
             format!("mixer_{}_audio_capsfilter", config.name).as_str(),
         )?;
-        let audio_caps = gst::Caps::builder("audio/x-raw")
-            .field("channels", &2)
-            .field("layout", &"interleaved")
-            .field("format", &"S32LE")
-            .build();
-        audio_capsfilter.set_property("caps", &audio_caps)?;
+        audio_capsfilter.set_property("caps", &config.audio.caps())?;
 
         let audio_tee =
             gst_create_element("tee", format!("mixer_{}_audio_tee", config.name).as_str())?;
         audio_tee.set_property("allow-not-linked", &true)?;
 
-        pipeline.add_many(&[&audio_mixer, &volume, &audio_capsfilter, &audio_tee])?;
-        gst::Element::link_many(&[&audio_mixer, &volume, &audio_capsfilter, &audio_tee])?;
+        let mut audio_elements = vec![audio_mixer.clone(), volume];
+        if let Some(loudness) = config.audio.loudness.as_ref() {
+            audio_elements.extend(create_loudness_elements(&config.name, loudness)?);
+        }
+        audio_elements.push(audio_capsfilter);
+        audio_elements.push(audio_tee.clone());
+        let audio_elements: Vec<&gst::Element> = audio_elements.iter().collect();
+
+        pipeline.add_many(&audio_elements)?;
+        gst::Element::link_many(&audio_elements)?;
 
         let mixer = Mixer {
             config: config.clone(),
+            active_input: None,
+            audio_bus: AudioBus::default(),
             pipeline,
             join_handle: None,
             audio_mixer,
@@ -102,13 +251,23 @@ impl Mixer {
             outputs: HashMap::new(),
             audio_out: audio_tee,
             video_out: video_tee,
+            loudness: Arc::new(Mutex::new(None)),
+            events: Arc::new(Mutex::new(VecDeque::new())),
+            tally: HashMap::new(),
+            iso: None,
+            eos: Arc::new(AtomicBool::new(false)),
+            snapshot,
         };
 
         let config = input::Config {
             name: "background".to_string(),
-            audio: AudioConfig { volume: 0.0 },
+            audio: AudioConfig {
+                volume: 0.0,
+                ..AudioConfig::default()
+            },
             video: config.video,
             record: false,
+            playback: PlaybackConfig::default(),
         };
 
         let mut background = input::Input::create_test(config)?;
@@ -135,15 +294,80 @@ impl Mixer {
         // TODO: Handle pending states
         let state = self.pipeline.get_state(gst::ClockTime::from_seconds(15)).1;
         input.set_state(state)?;
+        input.set_audio_format(&self.config.audio)?;
+        let replay_pad = match &input {
+            input::Input::Replay(replay) => Some(self.replay_source_pad(&replay.source)?),
+            _ => None,
+        };
         input.link(
             self.pipeline.clone(),
             self.audio_mixer.clone(),
             self.video_mixer.clone(),
         )?;
+        // Only start buffering once linking can no longer fail, so a failed input leaves no probe
+        // on its source behind.
+        if let Some(pad) = replay_pad {
+            let attached = match &mut input {
+                input::Input::Replay(replay) => replay.attach(pad),
+                _ => Ok(()),
+            };
+            if let Err(e) = attached {
+                input.set_state(gst::State::Null)?;
+                input.unlink()?;
+                return Err(e);
+            }
+        }
+
+        // The input is already part of the program, so a failing recording must not fail adding it.
+        let record = input.config().record;
+        if record && self.iso.is_none() {
+            if let Err(e) = self.iso_start(IsoConfig::default(), Some(vec![])) {
+                tracing::warn!("Failed to start ISO recording: {}", e);
+            }
+        }
+        let running_time = self.running_time();
+        if let Some(iso) = self.iso.as_mut() {
+            if record || iso.all_inputs() {
+                if let Err(e) = iso.record(&input, running_time) {
+                    tracing::warn!(
+                        input = input.name().as_str(),
+                        "Failed to record input: {}",
+                        e
+                    );
+                }
+            }
+        }
+
+        if let Some(tee) = input.video_tee() {
+            for output in self.outputs.values_mut() {
+                if let output::Output::Multiviewer(multiviewer) = output {
+                    if let Err(e) = multiviewer.add_input(&input.name(), &tee) {
+                        tracing::warn!(input = input.name().as_str(), "Failed to add tile: {}", e);
+                    }
+                }
+            }
+        }
 
         self.inputs.insert(input.name(), input);
+        self.tally_update();
 
-        Ok(())
+        self.audio_update()
+    }
+
+    /// Returns the pad carrying the video of a replay source, the program output for
+    /// [`PROGRAM`](../input/replay/constant.PROGRAM.html) or otherwise the input of that name.
+    fn replay_source_pad(&self, source: &str) -> Result<gst::Pad> {
+        if source == input::replay::PROGRAM {
+            return self.video_out.get_static_pad("sink").ok_or_else(|| {
+                Error::Gstreamer("failed to retrieve sink pad of video tee".to_string())
+            });
+        }
+
+        self.inputs
+            .get(source)
+            .ok_or_else(|| Error::NotFound("input".to_string(), source.to_string()))?
+            .video_pad()
+            .ok_or_else(|| Error::InvalidConfig(format!("input '{}' has no video", source)))
     }
 
     pub fn input_remove(&mut self, name: &str) -> Result<()> {
@@ -151,6 +375,19 @@ impl Mixer {
             return Err(Error::NotFound("input".to_string(), name.to_string()));
         }
 
+        let running_time = self.running_time();
+        if let Some(iso) = self.iso.as_mut() {
+            if iso.recording(name) {
+                iso.stop_input(name, running_time)?;
+            }
+        }
+
+        for output in self.outputs.values_mut() {
+            if let output::Output::Multiviewer(multiviewer) = output {
+                multiviewer.remove_input(name)?;
+            }
+        }
+
         let input = self
             .inputs
             .get_mut(name)
@@ -159,7 +396,15 @@ impl Mixer {
         input.unlink()?;
         self.inputs.remove(name);
 
-        Ok(())
+        if self.active_input.as_deref() == Some(name) {
+            self.active_input = None;
+        }
+        self.audio_bus
+            .pairings
+            .retain(|video, audio| video != name && audio != name);
+        self.tally_update();
+
+        self.audio_update()
     }
 
     pub fn output_count(&self) -> usize {
@@ -180,6 +425,22 @@ impl Mixer {
             self.video_out.clone(),
         )?;
 
+        if let output::Output::Multiviewer(multiviewer) = &mut output {
+            let mut names: Vec<&String> = self.inputs.keys().collect();
+            names.sort();
+            let added = names
+                .into_iter()
+                .filter_map(|name| self.inputs[name].video_tee().map(|tee| (name, tee)))
+                .try_for_each(|(name, tee)| multiviewer.add_input(name, &tee));
+            // A multiviewer which failed to add a tile must not leave its elements in the pipeline.
+            if let Err(e) = added {
+                output.set_state(gst::State::Null)?;
+                output.unlink()?;
+                return Err(e);
+            }
+            multiviewer.set_tally(&self.tally());
+        }
+
         self.outputs.insert(output.name(), output);
 
         Ok(())
@@ -194,6 +455,14 @@ impl Mixer {
             .outputs
             .get_mut(name)
             .ok_or_else(|| Error::NotFound("mixer".to_string(), name.to_string()))?;
+        if let output::Output::File(file) = output {
+            // Files are only complete once the muxer has seen the end of the stream.
+            if self.pipeline.get_state(gst::ClockTime::from_seconds(0)).1 == gst::State::Playing
+                && !file.finalize(std::time::Duration::from_secs(FINALIZE_TIMEOUT_S))?
+            {
+                tracing::warn!(output = name, "Recording was not finalized in time");
+            }
+        }
         output.set_state(gst::State::Null)?;
         output.unlink()?;
         self.outputs.remove(name);
@@ -203,13 +472,48 @@ impl Mixer {
 
     pub fn play(&mut self) -> Result<()> {
         let p = self.pipeline.clone();
-        self.join_handle = Some(std::thread::spawn(move || watch_bus(p)));
+        let loudness = self.loudness.clone();
+        let events = self.events.clone();
+        let eos = self.eos.clone();
+        self.join_handle = Some(std::thread::spawn(move || {
+            watch_bus(p, loudness, events, eos)
+        }));
 
         self.pipeline.set_state(gst::State::Playing)?;
         Ok(())
     }
 
+    /// Ends the streams of every input, so the outputs write out what they have and finish.
+    pub fn send_eos(&self) -> bool {
+        self.pipeline.send_event(gst::event::Eos::new())
+    }
+
+    /// Waits until `deadline` for the end of stream sent by [`send_eos`](#method.send_eos) to
+    /// reach every output, finalizing ISO recordings meanwhile, then stops the mixer. Returns
+    /// whether all outputs finished in time.
+    pub fn shutdown(&mut self, deadline: std::time::Instant) -> Result<bool> {
+        let mut finished = true;
+        if let Some(iso) = self.iso.take() {
+            finished = iso.finish(self.running_time(), deadline)?;
+        }
+
+        // The pipeline posts its end of stream once it reached every output.
+        while self.join_handle.is_some() && !self.eos.load(Ordering::SeqCst) {
+            if std::time::Instant::now() >= deadline {
+                finished = false;
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+
+        self.stop()?;
+        Ok(finished)
+    }
+
     pub fn stop(&mut self) -> Result<()> {
+        if self.iso.is_some() {
+            self.iso_stop()?;
+        }
         self.pipeline.set_state(gst::State::Null)?;
 
         /* TODO: Fix me
@@ -221,6 +525,76 @@ impl Mixer {
         Ok(())
     }
 
+    /// Starts an ISO recording session. Only the given inputs are recorded, or every input
+    /// including those added later when `inputs` is `None`.
+    pub fn iso_start(&mut self, config: IsoConfig, inputs: Option<Vec<String>>) -> Result<()> {
+        if self.iso.is_some() {
+            return Err(Error::InvalidConfig(
+                "an ISO recording is already running".to_string(),
+            ));
+        }
+        if let Some(names) = inputs.as_ref() {
+            if let Some(name) = names.iter().find(|name| !self.inputs.contains_key(*name)) {
+                return Err(Error::NotFound("input".to_string(), name.to_string()));
+            }
+        }
+
+        let running_time = self.running_time();
+        let mut session =
+            iso::Session::new(&self.config.name, config, inputs.is_none(), running_time)?;
+        for input in self.inputs.values() {
+            let selected = match inputs.as_ref() {
+                Some(names) => names.contains(&input.name()),
+                None => true,
+            };
+            if selected || input.config().record {
+                if let Err(e) = session.record(input, running_time) {
+                    // Finalize the files of the inputs already being recorded
+                    if let Err(stop_error) = session.stop(running_time) {
+                        tracing::warn!("Failed to stop ISO recording: {}", stop_error);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        self.iso = Some(session);
+
+        Ok(())
+    }
+
+    /// Stops the ISO recording session and returns its manifest.
+    pub fn iso_stop(&mut self) -> Result<iso::Manifest> {
+        let session = self
+            .iso
+            .take()
+            .ok_or_else(|| Error::InvalidConfig("no ISO recording is running".to_string()))?;
+
+        session.stop(self.running_time())
+    }
+
+    /// Returns the manifest of the running ISO recording session.
+    pub fn iso(&self) -> Option<iso::Manifest> {
+        self.iso.as_ref().map(|iso| iso.manifest().clone())
+    }
+
+    /// Returns the latest frame of the program, to be encoded with
+    /// [`snapshot::encode`](../snapshot/fn.encode.html).
+    pub fn snapshot(&self) -> Result<gst::Sample> {
+        self.snapshot.latest()
+    }
+
+    fn running_time(&self) -> u64 {
+        self.pipeline
+            .get_current_running_time()
+            .nseconds()
+            .unwrap_or(0)
+    }
+
+    /// Returns an element of the pipeline by the name it was created with.
+    pub fn element(&self, name: &str) -> Option<gst::Element> {
+        self.pipeline.get_by_name(name)
+    }
+
     pub fn generate_dot(&self) -> String {
         self.pipeline
             .debug_to_dot_data(gst::DebugGraphDetails::ALL)
@@ -252,23 +626,277 @@ impl Mixer {
         input.set_height(self.config.video.height, false)?;
 
         let input_config = input.config();
-        input.set_volume(input_config.audio.volume, false)?;
 
-        // Decrease volume and restore zorder of all other inputs
+        // Restore zorder of all other inputs
         for (n, input) in self.inputs.iter_mut() {
             if n != name {
-                input.set_volume(0.0, false)?;
                 // zorder should never be empty, we default it in the config.
                 input.set_zorder(input_config.video.zorder.unwrap_or(0), false)?;
             }
         }
 
+        self.active_input = Some(name.to_string());
+        self.tally_update();
+        self.audio_update()
+    }
+
+    /// Returns the tally of every input, as of the last switch or layout change.
+    pub fn tally(&self) -> HashMap<String, Tally> {
+        self.tally.clone()
+    }
+
+    /// Computes the tally of every input. While the active input covers the program no other input
+    /// is visible, otherwise every input with some opacity inside the frame is. Keyed inputs never
+    /// cover the program, and the alpha is read from the compositor as crossfades don't store it.
+    fn compute_tally(&self) -> HashMap<String, Tally> {
+        let covered = self
+            .active_input
+            .as_ref()
+            .and_then(|name| self.inputs.get(name))
+            .filter(|input| input.config().video.chroma_key.is_none())
+            .and_then(|input| input.video_pad())
+            .and_then(|pad| pad.get_property("alpha").ok())
+            .and_then(|alpha| alpha.get_some::<f64>().ok())
+            .map(|alpha| alpha >= 1.0)
+            .unwrap_or(false);
+
+        self.inputs
+            .iter()
+            .map(|(name, input)| {
+                let tally = if self.active_input.as_ref() == Some(name) {
+                    Tally::OnAir
+                } else if !covered
+                    && input.video_pad().is_some()
+                    && self.in_frame(&input.config().video)
+                {
+                    Tally::Visible
+                } else {
+                    Tally::Off
+                };
+                (name.clone(), tally)
+            })
+            .collect()
+    }
+
+    /// Returns whether video placed by `video` shows up in the program.
+    fn in_frame(&self, video: &VideoConfig) -> bool {
+        video.alpha > 0.0
+            && video.width > 0
+            && video.height > 0
+            && video.xpos < self.config.video.width
+            && video.ypos < self.config.video.height
+            && video.xpos + video.width > 0
+            && video.ypos + video.height > 0
+    }
+
+    /// Recomputes the tally after the active input or the layout has changed, shows it on the
+    /// multiviewers and records an event for every input whose tally changed.
+    pub fn tally_update(&mut self) {
+        let tally = self.compute_tally();
+        for output in self.outputs.values() {
+            if let output::Output::Multiviewer(multiviewer) = output {
+                multiviewer.set_tally(&tally);
+            }
+        }
+
+        let mut names: Vec<&String> = tally.keys().collect();
+        names.sort();
+        for name in names {
+            if self.tally.get(name) != tally.get(name) {
+                let event = Event::TallyChanged {
+                    input: name.clone(),
+                    tally: tally[name],
+                    timestamp: timestamp(),
+                };
+                tracing::debug!("{}: {:?}", self.config.name, event);
+                push_event(&self.events, event);
+            }
+        }
+
+        self.tally = tally;
+    }
+
+    /// Returns the recent events of the mixer, oldest first.
+    pub fn events(&self) -> Vec<Event> {
+        self.events
+            .lock()
+            .map(|events| events.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns the most recent loudness readings of the program audio. Returns `None` when
+    /// loudness measurement is disabled or nothing has been measured yet.
+    pub fn loudness(&self) -> Option<Loudness> {
+        self.loudness
+            .lock()
+            .ok()
+            .and_then(|loudness| loudness.clone())
+    }
+
+    pub fn active_input(&self) -> Option<String> {
+        self.active_input.clone()
+    }
+
+    /// Returns the input currently used as the program audio source. This is the input paired
+    /// with the active input on the audio bus, or the active input itself.
+    pub fn program_audio_input(&self) -> Option<String> {
+        self.active_input.as_ref().map(|active| {
+            self.audio_bus
+                .pairings
+                .get(active)
+                .unwrap_or(active)
+                .clone()
+        })
+    }
+
+    /// Returns whether the given input is currently heard on the program.
+    pub fn input_audible(&self, name: &str) -> bool {
+        let input = match self.inputs.get(name) {
+            Some(input) => input,
+            None => return false,
+        };
+
+        let config = input.config();
+        if config.audio.mute {
+            return false;
+        }
+
+        match config.audio.mode {
+            AudioMode::Off => false,
+            AudioMode::AlwaysOn => true,
+            // Until an input has been set active every input is heard.
+            AudioMode::FollowVideo => self
+                .program_audio_input()
+                .map(|program| program == name)
+                .unwrap_or(true),
+        }
+    }
+
+    pub fn audio_bus(&self) -> AudioBus {
+        self.audio_bus.clone()
+    }
+
+    pub fn set_audio_bus(&mut self, audio_bus: AudioBus) -> Result<()> {
+        for (video, audio) in audio_bus.pairings.iter() {
+            for name in &[video, audio] {
+                if !self.inputs.contains_key(name.as_str()) {
+                    return Err(Error::NotFound("input".to_string(), name.to_string()));
+                }
+            }
+        }
+
+        self.audio_bus = audio_bus;
+        self.audio_update()
+    }
+
+    /// Applies the audio mode of every input, muting the inputs which should not be heard on the
+    /// program and restoring the configured volume of those that should.
+    pub fn audio_update(&mut self) -> Result<()> {
+        let volumes: Vec<(String, f64)> = self
+            .inputs
+            .iter()
+            .map(|(name, input)| {
+                let volume = match self.input_audible(name) {
+                    true => input.config().audio.volume,
+                    false => 0.0,
+                };
+                (name.clone(), volume)
+            })
+            .collect();
+
+        for (name, volume) in volumes {
+            if let Some(input) = self.inputs.get_mut(&name) {
+                input.set_volume(volume, false)?;
+            }
+        }
+
         Ok(())
     }
 }
 
-fn watch_bus(pipeline: gst::Pipeline) {
-    // Wait until error or EOS
+/// Creates the elements measuring, and optionally normalizing, the loudness of the program audio.
+/// `audioloudnorm` only operates at 192kHz, so the audio is converted around it.
+fn create_loudness_elements(name: &str, config: &LoudnessConfig) -> Result<Vec<gst::Element>> {
+    // Both elements come from the audiofx plugin of gst-plugins-rs, which few installs include.
+    let mut factories = vec!["ebur128level"];
+    if config.normalize {
+        factories.push("audioloudnorm");
+    }
+    if let Some(missing) = factories
+        .iter()
+        .find(|factory| gst::ElementFactory::find(factory).is_none())
+    {
+        return Err(Error::InvalidConfig(format!(
+            "loudness needs the `{}` element of the gst-plugins-rs audiofx plugin, which is not \
+             installed",
+            missing
+        )));
+    }
+
+    let mut elements = vec![];
+
+    if config.normalize {
+        let convert = gst_create_element(
+            "audioconvert",
+            format!("mixer_{}_loudnorm_convert", name).as_str(),
+        )?;
+        let resample = gst_create_element(
+            "audioresample",
+            format!("mixer_{}_loudnorm_resample", name).as_str(),
+        )?;
+        let loudnorm = gst_create_element(
+            "audioloudnorm",
+            format!("mixer_{}_audio_loudnorm", name).as_str(),
+        )?;
+        loudnorm.set_property("loudness-target", &config.target)?;
+        loudnorm.set_property("max-true-peak", &config.max_true_peak)?;
+        elements.extend(vec![convert, resample, loudnorm]);
+    }
+
+    let convert = gst_create_element(
+        "audioconvert",
+        format!("mixer_{}_ebur128_convert", name).as_str(),
+    )?;
+    let resample = gst_create_element(
+        "audioresample",
+        format!("mixer_{}_ebur128_resample", name).as_str(),
+    )?;
+    let level = gst_create_element(
+        "ebur128level",
+        format!("mixer_{}_audio_ebur128level", name).as_str(),
+    )?;
+    level.set_property("post-messages", &true)?;
+    // Post readings often enough for the momentary loudness to be useful.
+    level.set_property("interval", &100_000_000u64)?;
+    elements.extend(vec![convert, resample, level]);
+
+    Ok(elements)
+}
+
+/// Describes a GStreamer error by its domain and code only. The message and debugging information
+/// of an element may include its location, and with it a password or stream key.
+fn error_kind(error: &gst::glib::Error) -> String {
+    if let Some(kind) = error.kind::<gst::ResourceError>() {
+        format!("resource error {:?}", kind)
+    } else if let Some(kind) = error.kind::<gst::StreamError>() {
+        format!("stream error {:?}", kind)
+    } else if let Some(kind) = error.kind::<gst::CoreError>() {
+        format!("core error {:?}", kind)
+    } else if let Some(kind) = error.kind::<gst::LibraryError>() {
+        format!("library error {:?}", kind)
+    } else {
+        "unknown error".to_string()
+    }
+}
+
+fn watch_bus(
+    pipeline: gst::Pipeline,
+    loudness: Arc<Mutex<Option<Loudness>>>,
+    events: Arc<Mutex<VecDeque<Event>>>,
+    eos: Arc<AtomicBool>,
+) {
+    // Wait until EOS. Errors of single elements, such as an output losing its connection, don't
+    // end the program.
     let bus = pipeline.get_bus().unwrap();
     for msg in bus.iter_timed(gst::CLOCK_TIME_NONE) {
         use gst::MessageView;
@@ -278,14 +906,11 @@ fn watch_bus(pipeline: gst::Pipeline) {
                     "{}: Error received from element {:?} {}",
                     pipeline.get_name(),
                     err.get_src().map(|s| s.get_path_string()),
-                    err.get_error()
+                    error_kind(&err.get_error())
                 );
-                eprintln!(
-                    "{}: Debugging information: {:?}",
-                    pipeline.get_name(),
-                    err.get_debug()
-                );
-                break;
+                if let Some(src) = err.get_src() {
+                    input::playlist::end_failed_item(&src);
+                }
             }
             MessageView::StateChanged(state_changed) => {
                 if state_changed
@@ -306,8 +931,58 @@ fn watch_bus(pipeline: gst::Pipeline) {
                     }
                 }
             }
-            MessageView::Eos(..) => break,
+            MessageView::Application(application) => {
+                let structure = match application.get_structure() {
+                    Some(structure) if structure.get_name() == "playlist-finished" => structure,
+                    _ => continue,
+                };
+
+                let event = Event::PlaylistFinished {
+                    input: structure
+                        .get::<String>("input")
+                        .ok()
+                        .flatten()
+                        .unwrap_or_default(),
+                    timestamp: timestamp(),
+                };
+                tracing::info!("{}: {:?}", pipeline.get_name(), event);
+                push_event(&events, event);
+            }
+            MessageView::Element(element) => {
+                let structure = match element.get_structure() {
+                    Some(structure) if structure.get_name() == "ebur128-level" => structure,
+                    _ => continue,
+                };
+
+                if let (Some(reading), Ok(mut loudness)) =
+                    (Loudness::from_structure(structure), loudness.lock())
+                {
+                    *loudness = Some(reading);
+                }
+            }
+            MessageView::Eos(..) => {
+                eos.store(true, Ordering::SeqCst);
+                break;
+            }
             _ => (),
         }
     }
 }
+
+/// Appends an event, dropping the oldest once `MAX_EVENTS` are kept.
+fn push_event(events: &Mutex<VecDeque<Event>>, event: Event) {
+    if let Ok(mut events) = events.lock() {
+        if events.len() == MAX_EVENTS {
+            events.pop_front();
+        }
+        events.push_back(event);
+    }
+}
+
+/// Milliseconds since the unix epoch.
+fn timestamp() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
@@ -9,6 +9,9 @@ pub enum Error {
     #[error("{0}")]
     System(String),
 
+    #[error("invalid configuration: {0}")]
+    InvalidConfig(String),
+
     #[error("a {0} with the name '{1}' was not found")]
     NotFound(String, String),
 
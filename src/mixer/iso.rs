@@ -0,0 +1,326 @@
+use super::Error;
+use crate::{gst_create_element, input, IsoConfig, Result};
+
+use chrono::{DateTime, Utc};
+use gst::prelude::*;
+use gstreamer as gst;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+/// How long a stopped recording may take to finalize its file.
+const FINALIZE_TIMEOUT_S: u64 = 10;
+
+/// Manifest of an ISO recording session, written next to the recordings.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Manifest {
+    pub mixer: String,
+    pub session: String,
+    pub started_at: DateTime<Utc>,
+    /// Running time of the program when the session started.
+    pub start_ms: u64,
+    pub files: Vec<ManifestFile>,
+}
+
+/// Recording of a single input in a [`Manifest`](struct.Manifest.html).
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ManifestFile {
+    pub input: String,
+    pub file: String,
+    /// Running time of the program the first frame of the file was recorded at.
+    pub start_ms: u64,
+    /// Running time of the program the recording was stopped at.
+    pub stop_ms: Option<u64>,
+}
+
+/// Isolated recording of every input of a mixer into its own file. Each file is written by a
+/// separate pipeline fed from the input's tees, ahead of the mixer, so a slow encoder or disk never
+/// holds up the program.
+pub(super) struct Session {
+    config: IsoConfig,
+    /// Records inputs added during the session, otherwise only those asking to be recorded.
+    all_inputs: bool,
+    manifest: Manifest,
+    manifest_path: PathBuf,
+    recordings: HashMap<String, Recording>,
+    /// Threads finalizing the files of stopped recordings.
+    finishing: Vec<std::thread::JoinHandle<()>>,
+}
+
+struct Recording {
+    pipeline: gst::Pipeline,
+    sources: Vec<gst::Element>,
+    probes: Vec<(gst::Pad, gst::PadProbeId)>,
+}
+
+impl Session {
+    pub fn new(
+        mixer: &str,
+        config: IsoConfig,
+        all_inputs: bool,
+        running_time: u64,
+    ) -> Result<Self> {
+        if !config.template.contains("{input}") {
+            return Err(Error::InvalidConfig(
+                "the ISO filename template must contain {input}".to_string(),
+            ));
+        }
+        // Recordings stay within the directory
+        if config.template.contains('/')
+            || config.template.contains('\\')
+            || config.template.contains("..")
+        {
+            return Err(Error::InvalidConfig(
+                "the ISO filename template must not contain a path".to_string(),
+            ));
+        }
+        std::fs::create_dir_all(&config.directory).map_err(|e| Error::System(e.to_string()))?;
+
+        // Sessions started within the same second still get their own files
+        let started_at = Utc::now();
+        let session = started_at.format("%Y%m%dT%H%M%S%.3fZ").to_string();
+        let manifest_path = PathBuf::from(&config.directory).join(format!(
+            "{}_{}.json",
+            clean_name(mixer),
+            session
+        ));
+        if manifest_path.exists() {
+            return Err(Error::Exists(
+                "ISO manifest".to_string(),
+                manifest_path.to_string_lossy().to_string(),
+            ));
+        }
+
+        let session = Session {
+            config,
+            all_inputs,
+            manifest: Manifest {
+                mixer: mixer.to_string(),
+                session,
+                started_at,
+                start_ms: running_time / gst::MSECOND_VAL,
+                files: vec![],
+            },
+            manifest_path,
+            recordings: HashMap::new(),
+            finishing: vec![],
+        };
+        session.write_manifest()?;
+
+        Ok(session)
+    }
+
+    pub fn all_inputs(&self) -> bool {
+        self.all_inputs
+    }
+
+    pub fn recording(&self, input: &str) -> bool {
+        self.recordings.contains_key(input)
+    }
+
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+
+    /// Starts recording a linked input, from `running_time` of the program onwards.
+    pub fn record(&mut self, input: &input::Input, running_time: u64) -> Result<()> {
+        let name = input.name();
+        if self.recordings.contains_key(&name) {
+            return Err(Error::Exists("recording".to_string(), name));
+        }
+
+        let file = PathBuf::from(&self.config.directory).join(
+            self.config
+                .template
+                .replace("{mixer}", &clean_name(&self.manifest.mixer))
+                .replace("{input}", &clean_name(&name))
+                .replace("{session}", &self.manifest.session),
+        );
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| Error::System(e.to_string()))?;
+        }
+        let file = file.to_string_lossy().to_string();
+        // Templates without {session} would overwrite the recordings of earlier sessions
+        if std::path::Path::new(&file).exists() {
+            return Err(Error::Exists("recording file".to_string(), file));
+        }
+
+        let recording = Recording::create(&name, &file, input, running_time)?;
+        self.recordings.insert(name.clone(), recording);
+        self.manifest.files.push(ManifestFile {
+            input: name,
+            file,
+            start_ms: running_time / gst::MSECOND_VAL,
+            stop_ms: None,
+        });
+        self.write_manifest()
+    }
+
+    /// Stops recording an input, finalizing its file in the background.
+    pub fn stop_input(&mut self, name: &str, running_time: u64) -> Result<()> {
+        let recording = self
+            .recordings
+            .remove(name)
+            .ok_or_else(|| Error::NotFound("recording".to_string(), name.to_string()))?;
+        self.finishing.push(recording.finish());
+
+        for file in self.manifest.files.iter_mut() {
+            if file.input == name && file.stop_ms.is_none() {
+                file.stop_ms = Some(running_time / gst::MSECOND_VAL);
+            }
+        }
+        self.write_manifest()
+    }
+
+    /// Stops every recording of the session and returns its final manifest.
+    pub fn stop(mut self, running_time: u64) -> Result<Manifest> {
+        self.stop_recordings(running_time)?;
+        Ok(self.manifest)
+    }
+
+    /// Stops every recording of the session like [`stop`](#method.stop), but waits until
+    /// `deadline` for their files to be finalized. Returns whether they were.
+    pub fn finish(mut self, running_time: u64, deadline: Instant) -> Result<bool> {
+        self.stop_recordings(running_time)?;
+        while !self.finishing.iter().all(|handle| handle.is_finished()) {
+            if Instant::now() >= deadline {
+                return Ok(false);
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+
+        Ok(true)
+    }
+
+    fn stop_recordings(&mut self, running_time: u64) -> Result<()> {
+        let names: Vec<String> = self.recordings.keys().cloned().collect();
+        for name in names {
+            self.stop_input(&name, running_time)?;
+        }
+        Ok(())
+    }
+
+    fn write_manifest(&self) -> Result<()> {
+        let manifest = serde_json::to_string_pretty(&self.manifest)
+            .map_err(|e| Error::System(e.to_string()))?;
+        std::fs::write(&self.manifest_path, manifest).map_err(|e| Error::System(e.to_string()))
+    }
+}
+
+impl Recording {
+    fn create(name: &str, file: &str, input: &input::Input, running_time: u64) -> Result<Self> {
+        let audio_pad = tap_point(input.audio_tee(), input.audio_pad())
+            .ok_or_else(|| Error::Gstreamer(format!("input '{}' is not linked", name)))?;
+
+        let pipeline = gst::Pipeline::new(Some(&format!("iso_{}", name)));
+        let mux = gst_create_element("matroskamux", &format!("iso_{}_mux", name))?;
+        let sink = gst_create_element("filesink", &format!("iso_{}_sink", name))?;
+        sink.set_property("location", &file)?;
+        pipeline.add_many(&[&mux, &sink])?;
+        gst::Element::link_many(&[&mux, &sink])?;
+
+        let mut sources = vec![];
+        let mut pads = vec![];
+
+        if let Some(video_pad) = tap_point(input.video_tee(), input.video_pad()) {
+            let src = gst_create_element("appsrc", &format!("iso_{}_video_src", name))?;
+            src.set_property_from_str("format", "time");
+            let queue = gst_create_element("queue", &format!("iso_{}_video_queue", name))?;
+            let convert =
+                gst_create_element("videoconvert", &format!("iso_{}_video_convert", name))?;
+            let encoder = gst_create_element("x264enc", &format!("iso_{}_video_x264enc", name))?;
+            encoder.set_property_from_str("tune", "zerolatency");
+            encoder.set_property_from_str("speed-preset", "veryfast");
+            let parse = gst_create_element("h264parse", &format!("iso_{}_video_parse", name))?;
+
+            let elements = [&src, &queue, &convert, &encoder, &parse, &mux];
+            pipeline.add_many(&elements[..5])?;
+            gst::Element::link_many(&elements)?;
+            sources.push(src);
+            pads.push(video_pad);
+        }
+
+        let src = gst_create_element("appsrc", &format!("iso_{}_audio_src", name))?;
+        src.set_property_from_str("format", "time");
+        let queue = gst_create_element("queue", &format!("iso_{}_audio_queue", name))?;
+        let convert = gst_create_element("audioconvert", &format!("iso_{}_audio_convert", name))?;
+        let resample =
+            gst_create_element("audioresample", &format!("iso_{}_audio_resample", name))?;
+        let encoder = gst_create_element("opusenc", &format!("iso_{}_audio_opusenc", name))?;
+
+        let elements = [&src, &queue, &convert, &resample, &encoder, &mux];
+        pipeline.add_many(&elements[..5])?;
+        gst::Element::link_many(&elements)?;
+        sources.push(src);
+        pads.push(audio_pad);
+
+        pipeline.set_state(gst::State::Playing)?;
+
+        let mut probes = vec![];
+        for (pad, src) in pads.into_iter().zip(sources.iter()) {
+            let probe = input::tap_pad(&pad, src, running_time)?;
+            probes.push((pad, probe));
+        }
+
+        Ok(Recording {
+            pipeline,
+            sources,
+            probes,
+        })
+    }
+
+    /// Stops feeding the recording and ends its streams, so the muxer can write its index. The
+    /// pipeline is shut down once the file is complete.
+    fn finish(self) -> std::thread::JoinHandle<()> {
+        for (pad, probe) in self.probes {
+            pad.remove_probe(probe);
+        }
+        for src in self.sources.iter() {
+            let _ = src.emit("end-of-stream", &[]);
+        }
+
+        let pipeline = self.pipeline;
+        std::thread::spawn(move || {
+            if let Some(bus) = pipeline.get_bus() {
+                let message = bus.timed_pop_filtered(
+                    gst::ClockTime::from_seconds(FINALIZE_TIMEOUT_S),
+                    &[gst::MessageType::Eos, gst::MessageType::Error],
+                );
+                if message.map(|m| m.get_type()) != Some(gst::MessageType::Eos) {
+                    tracing::warn!(
+                        pipeline = pipeline.get_name().as_str(),
+                        "ISO recording did not finalize cleanly"
+                    );
+                }
+            }
+            let _ = pipeline.set_state(gst::State::Null);
+        })
+    }
+}
+
+/// Returns the sink pad of an input's tee, which sees the input before the mixer does, or
+/// otherwise the mixer pad of inputs without one.
+fn tap_point(tee: Option<gst::Element>, mixer_pad: Option<gst::Pad>) -> Option<gst::Pad> {
+    // Only linked inputs have a mixer pad, and data on their tees
+    let mixer_pad = mixer_pad?;
+    Some(
+        tee.and_then(|tee| tee.get_static_pad("sink"))
+            .unwrap_or(mixer_pad),
+    )
+}
+
+/// Replaces characters which are awkward in file names.
+fn clean_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
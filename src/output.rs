@@ -1,17 +1,26 @@
+pub mod audio_file;
 pub mod auto;
 pub mod fake;
 pub mod file;
+pub mod icecast;
+pub mod multiviewer;
 pub mod rtmp;
 
 use crate::mixer::Error as MixerError;
 use crate::Result;
-use crate::{AudioConfig, AudioEncoderConfig, Mux, VideoConfig, VideoEncoderConfig};
+use crate::{
+    gst_create_element, secret::SecretUrl, AudioConfig, AudioEncoder, AudioEncoderConfig,
+    MultiviewerConfig, Mux, SegmentConfig, VideoConfig, VideoEncoderConfig,
+};
 
+pub use audio_file::AudioFile;
 pub use auto::Auto;
 pub use fake::Fake;
 pub use file::File;
 use gst::prelude::*;
 use gstreamer as gst;
+pub use icecast::Icecast;
+pub use multiviewer::Multiviewer;
 pub use rtmp::RTMP;
 use serde::{Deserialize, Serialize};
 
@@ -48,6 +57,9 @@ pub enum Output {
     Auto(Auto),
     Fake(Fake),
     File(File),
+    Icecast(Icecast),
+    AudioFile(AudioFile),
+    Multiviewer(Multiviewer),
 }
 
 impl Output {
@@ -63,8 +75,24 @@ impl Output {
         Fake::create(config).map(Self::Fake)
     }
 
-    pub fn create_file(config: Config, location: &str) -> Result<Self> {
-        File::create(config, location).map(Self::File)
+    pub fn create_file(config: Config, location: &str, segment: &SegmentConfig) -> Result<Self> {
+        File::create(config, location, segment).map(Self::File)
+    }
+
+    pub fn create_icecast(config: Config, location: &str) -> Result<Self> {
+        Icecast::create(config, location).map(Self::Icecast)
+    }
+
+    pub fn create_audio_file(config: Config, location: &str) -> Result<Self> {
+        AudioFile::create(config, location).map(Self::AudioFile)
+    }
+
+    pub fn create_multiviewer(
+        config: Config,
+        sink: Output,
+        multiviewer: &MultiviewerConfig,
+    ) -> Result<Self> {
+        Multiviewer::create(config, sink, multiviewer).map(Self::Multiviewer)
     }
 
     pub fn name(&self) -> String {
@@ -73,6 +101,9 @@ impl Output {
             Output::Auto(output) => output.name(),
             Output::Fake(output) => output.name(),
             Output::File(output) => output.name(),
+            Output::Icecast(output) => output.name(),
+            Output::AudioFile(output) => output.name(),
+            Output::Multiviewer(output) => output.name(),
         }
     }
 
@@ -82,15 +113,22 @@ impl Output {
             Output::Auto(_) => "Auto".to_string(),
             Output::Fake(_) => "Fake".to_string(),
             Output::File(_) => "File".to_string(),
+            Output::Icecast(_) => "Icecast".to_string(),
+            Output::AudioFile(_) => "AudioFile".to_string(),
+            Output::Multiviewer(_) => "Multiviewer".to_string(),
         }
     }
 
-    pub fn location(&self) -> String {
+    /// Returns where the output sends to, which may hold a stream key or password.
+    pub fn location(&self) -> SecretUrl {
         match self {
-            Output::RTMP(output) => output.location.clone(),
-            Output::Auto(_) => "".to_string(),
-            Output::Fake(_) => "".to_string(),
-            Output::File(_) => "".to_string(),
+            Output::RTMP(output) => SecretUrl::new(output.location.as_str()),
+            Output::Auto(_) => SecretUrl::default(),
+            Output::Fake(_) => SecretUrl::default(),
+            Output::File(_) => SecretUrl::default(),
+            Output::Icecast(output) => SecretUrl::new(output.location.as_str()),
+            Output::AudioFile(output) => SecretUrl::new(output.location.as_str()),
+            Output::Multiviewer(output) => output.sink.location(),
         }
     }
 
@@ -105,6 +143,9 @@ impl Output {
             Output::Auto(output) => output.link(pipeline, audio, video),
             Output::Fake(output) => output.link(pipeline, audio, video),
             Output::File(output) => output.link(pipeline, audio, video),
+            Output::Icecast(output) => output.link(pipeline, audio, video),
+            Output::AudioFile(output) => output.link(pipeline, audio, video),
+            Output::Multiviewer(output) => output.link(pipeline, audio, video),
         }
     }
 
@@ -114,6 +155,9 @@ impl Output {
             Output::Auto(output) => output.unlink(),
             Output::Fake(output) => output.unlink(),
             Output::File(output) => output.unlink(),
+            Output::Icecast(output) => output.unlink(),
+            Output::AudioFile(output) => output.unlink(),
+            Output::Multiviewer(output) => output.unlink(),
         }
     }
 
@@ -123,10 +167,42 @@ impl Output {
             Output::Auto(output) => output.set_state(state),
             Output::Fake(output) => output.set_state(state),
             Output::File(output) => output.set_state(state),
+            Output::Icecast(output) => output.set_state(state),
+            Output::AudioFile(output) => output.set_state(state),
+            Output::Multiviewer(output) => output.set_state(state),
         }
     }
 }
 
+/// Creates the encoder for audio-only outputs, followed by whatever is needed to write its
+/// stream without a video muxer.
+fn create_audio_encoder(name: &str, encoder: &AudioEncoder) -> Result<Vec<gst::Element>> {
+    let audio_encoder = gst_create_element(
+        &encoder.to_string(),
+        &format!("output_{}_audio_{}", name, encoder),
+    )?;
+
+    let container = match encoder {
+        AudioEncoder::MP3 => None,
+        AudioEncoder::Vorbis | AudioEncoder::Opus => Some(gst_create_element(
+            "oggmux",
+            &format!("output_{}_audio_mux", name),
+        )?),
+        AudioEncoder::AAC => {
+            let capsfilter =
+                gst_create_element("capsfilter", &format!("output_{}_audio_capsfilter", name))?;
+            let caps = gst::Caps::builder("audio/mpeg")
+                .field("mpegversion", &4)
+                .field("stream-format", &"adts")
+                .build();
+            capsfilter.set_property("caps", &caps)?;
+            Some(capsfilter)
+        }
+    };
+
+    Ok(std::iter::once(audio_encoder).chain(container).collect())
+}
+
 fn release_request_pad(elem: &gst::Element) -> Result<()> {
     let pad = elem.get_static_pad("sink").ok_or_else(|| {
         MixerError::Gstreamer("Failed to get static sink pad for element".to_string())
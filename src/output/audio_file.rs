@@ -0,0 +1,86 @@
+use super::Config;
+use crate::{gst_create_element, Result};
+use gst::prelude::*;
+use gstreamer as gst;
+
+/// Audio-only output recording the program audio to a file.
+pub struct AudioFile {
+    pub name: String,
+    pub location: String,
+    pipeline: Option<gst::Pipeline>,
+    audio_queue: gst::Element,
+    audio_convert: gst::Element,
+    audio_resample: gst::Element,
+    audio_encoder: Vec<gst::Element>,
+    audio_sink: gst::Element,
+}
+
+impl AudioFile {
+    pub fn create(config: Config, location: &str) -> Result<Self> {
+        let name = &config.name;
+
+        let audio_queue = gst_create_element("queue", &format!("output_{}_audio_queue", name))?;
+        let audio_convert =
+            gst_create_element("audioconvert", &format!("output_{}_audio_convert", name))?;
+        let audio_resample =
+            gst_create_element("audioresample", &format!("output_{}_audio_resample", name))?;
+        let audio_encoder = super::create_audio_encoder(name, &config.encoder.audio.encoder)?;
+        let audio_sink = gst_create_element("filesink", &format!("output_{}_audio_sink", name))?;
+        audio_sink.set_property("location", &location)?;
+
+        Ok(Self {
+            name: name.to_string(),
+            location: location.to_string(),
+            pipeline: None,
+            audio_queue,
+            audio_convert,
+            audio_resample,
+            audio_encoder,
+            audio_sink,
+        })
+    }
+
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    pub fn link(
+        &mut self,
+        pipeline: gst::Pipeline,
+        audio: gst::Element,
+        _video: gst::Element,
+    ) -> Result<()> {
+        let elements = self.elements();
+        pipeline.add_many(&elements)?;
+
+        gst::Element::link_many(&[&audio, &self.audio_queue])?;
+        gst::Element::link_many(&elements)?;
+
+        self.pipeline = Some(pipeline);
+
+        Ok(())
+    }
+
+    pub fn unlink(&self) -> Result<()> {
+        super::release_request_pad(&self.audio_queue)?;
+
+        if let Some(pipeline) = self.pipeline.as_ref() {
+            pipeline.remove_many(&self.elements())?;
+        }
+        Ok(())
+    }
+
+    pub fn set_state(&mut self, state: gst::State) -> Result<()> {
+        for element in self.elements() {
+            element.set_state(state)?;
+        }
+        Ok(())
+    }
+
+    fn elements(&self) -> Vec<&gst::Element> {
+        let mut elements = vec![&self.audio_queue, &self.audio_convert, &self.audio_resample];
+        elements.extend(self.audio_encoder.iter());
+        elements.push(&self.audio_sink);
+        elements
+    }
+}
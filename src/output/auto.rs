@@ -39,8 +39,11 @@ impl Auto {
         )?;
 
         let video_caps = gst::Caps::builder("video/x-raw")
-            .field("framerate", &gst::Fraction::new(30, 1))
-            .field("format", &"I420")
+            .field("framerate", &gst::Fraction::new(config.video.framerate, 1))
+            .field("format", &config.video.format.to_string())
+            .field("width", &config.video.width)
+            .field("height", &config.video.height)
+            .field("pixel-aspect-ratio", &gst::Fraction::new(1, 1))
             .field("profile", &"high")
             .build();
         video_capsfilter.set_property("caps", &video_caps)?;
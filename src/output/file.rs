@@ -1,7 +1,16 @@
 use super::Config;
-use crate::{gst_create_element, Result, VideoEncoder, VideoEncoderProfile, VideoEncoderSpeed};
+use crate::mixer::Error as MixerError;
+use crate::{
+    gst_create_element, Mux, Result, SegmentConfig, VideoEncoder, VideoEncoderProfile,
+    VideoEncoderSpeed,
+};
 use gst::prelude::*;
 use gstreamer as gst;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+use std::time::{Duration, Instant};
 
 pub struct File {
     pub name: String,
@@ -15,18 +24,29 @@ pub struct File {
     video_encoder: gst::Element,
     encoder_parse: Option<gst::Element>,
     mux_queue: gst::Element,
-    output_mux: gst::Element,
-    queue_sink: gst::Element,
-    video_sink: gst::Element,
+    splitmux: gst::Element,
 
     audio_queue: gst::Element,
     audio_convert: gst::Element,
     audio_resample: gst::Element,
     audioenc: gst::Element,
+
+    /// Location of the first segment of the current recording, read when a segment is opened.
+    segment_location: Arc<Mutex<String>>,
+    /// Probes dropping the program on its way into the output while the recording is stopped.
+    gates: Vec<(gst::Pad, gst::PadProbeId)>,
+    recording: bool,
+    takes: u32,
+    /// Set by [`stop_recording`](#method.stop_recording), so the end of stream closing a segment
+    /// isn't taken for the end of the recording.
+    stopping: Arc<AtomicBool>,
+    /// Set once the end of stream of a stopped recording reached the file, after the muxer
+    /// finalized it.
+    finished: Arc<AtomicBool>,
 }
 
 impl File {
-    pub fn create(config: Config, location: &str) -> Result<Self> {
+    pub fn create(config: Config, location: &str, segment: &SegmentConfig) -> Result<Self> {
         let Config { name, .. } = config;
         // Video stream
         let video_queue = gst_create_element("queue", &format!("output_{}_video_queue", name))?;
@@ -42,6 +62,9 @@ impl File {
         let video_caps = gst::Caps::builder("video/x-raw")
             .field("framerate", &gst::Fraction::new(config.video.framerate, 1))
             .field("format", &config.video.format.to_string())
+            .field("width", &config.video.width)
+            .field("height", &config.video.height)
+            .field("pixel-aspect-ratio", &gst::Fraction::new(1, 1))
             .field(
                 "profile",
                 &config
@@ -78,13 +101,54 @@ impl File {
 
         let mux_queue =
             gst_create_element("queue", &format!("output_{}_video_output_queue", name))?;
-        let output_mux = gst_create_element("matroskamux", &format!("output_{}_output_mux", name))?;
-        output_mux.set_property_from_str("streamable", "true");
 
-        let queue_sink = gst_create_element("queue", &format!("output_{}_rtmp_queuesink", name))?;
-        let video_sink = gst_create_element("filesink", &format!("output_{}_file_sink", name))?;
-        // TODO: Configure recording directory, also use timestamp
-        video_sink.set_property("location", &location)?;
+        // The muxer and sink are owned by splitmuxsink, which finalizes each file it closes.
+        let output_mux = gst_create_element(
+            &config.mux.unwrap_or(Mux::MKV).to_string(),
+            &format!("output_{}_output_mux", name),
+        )?;
+        let file_sink = gst_create_element("filesink", &format!("output_{}_file_sink", name))?;
+        let splitmux = gst_create_element("splitmuxsink", &format!("output_{}_splitmux", name))?;
+        splitmux.set_property("muxer", &output_mux)?;
+        splitmux.set_property("sink", &file_sink)?;
+        splitmux.set_property(
+            "max-size-time",
+            &segment.max_duration_s.saturating_mul(gst::SECOND_VAL),
+        )?;
+        splitmux.set_property("max-size-bytes", &segment.max_size_bytes)?;
+
+        let segment_location = Arc::new(Mutex::new(location.to_string()));
+        let format_location = segment_location.clone();
+        let finished = Arc::new(AtomicBool::new(false));
+        let segment_finished = finished.clone();
+        splitmux.connect("format-location", false, move |args| {
+            // Every segment is closed with an end of stream, so a new one is not finished yet.
+            segment_finished.store(false, Ordering::SeqCst);
+            let fragment = args.get(1).and_then(|arg| arg.get_some::<u32>().ok())?;
+            let location = format_location.lock().ok()?;
+            let location = match fragment {
+                0 => location.clone(),
+                _ => numbered_location(&location, &format!("{:05}", fragment)),
+            };
+            Some(location.to_value())
+        })?;
+
+        let stopping = Arc::new(AtomicBool::new(false));
+        let sink_stopping = stopping.clone();
+        let sink_finished = finished.clone();
+        file_sink
+            .get_static_pad("sink")
+            .ok_or_else(|| MixerError::Gstreamer("Failed to get sink pad of file".to_string()))?
+            .add_probe(gst::PadProbeType::EVENT_DOWNSTREAM, move |_, info| {
+                if let Some(gst::PadProbeData::Event(event)) = &info.data {
+                    if event.get_type() == gst::EventType::Eos
+                        && sink_stopping.load(Ordering::SeqCst)
+                    {
+                        sink_finished.store(true, Ordering::SeqCst);
+                    }
+                }
+                gst::PadProbeReturn::Ok
+            });
 
         // Audio stream
         let audio_queue = gst_create_element("queue", &format!("output_{}_audio_queue", name))?;
@@ -107,13 +171,17 @@ impl File {
             video_encoder,
             encoder_parse,
             mux_queue,
-            output_mux,
-            queue_sink,
-            video_sink,
+            splitmux,
             audio_queue,
             audio_convert,
             audio_resample,
             audioenc,
+            segment_location,
+            gates: vec![],
+            recording: true,
+            takes: 0,
+            stopping,
+            finished,
         })
     }
 
@@ -136,9 +204,7 @@ impl File {
             &self.video_capsfilter,
             &self.video_encoder,
             &self.mux_queue,
-            &self.output_mux,
-            &self.queue_sink,
-            &self.video_sink,
+            &self.splitmux,
         ])?;
 
         if let Some(encoder_parse) = self.encoder_parse.as_ref() {
@@ -162,12 +228,7 @@ impl File {
             gst::Element::link_many(&[&self.video_encoder, &self.mux_queue])?;
         }
 
-        gst::Element::link_many(&[
-            &self.mux_queue,
-            &self.output_mux,
-            &self.queue_sink,
-            &self.video_sink,
-        ])?;
+        link_request_pad(&self.mux_queue, &self.splitmux, "video")?;
 
         // Audio
         pipeline.add_many(&[
@@ -183,9 +244,10 @@ impl File {
             &self.audio_convert,
             &self.audio_resample,
             &self.audioenc,
-            &self.output_mux,
         ])?;
 
+        link_request_pad(&self.audioenc, &self.splitmux, "audio_%u")?;
+
         self.pipeline = Some(pipeline);
 
         Ok(())
@@ -204,9 +266,7 @@ impl File {
                 &self.video_capsfilter,
                 &self.video_encoder,
                 &self.mux_queue,
-                &self.output_mux,
-                &self.queue_sink,
-                &self.video_sink,
+                &self.splitmux,
             ])?;
 
             if let Some(encoder_parse) = self.encoder_parse.as_ref() {
@@ -225,24 +285,174 @@ impl File {
     }
 
     pub fn set_state(&mut self, state: gst::State) -> Result<()> {
-        self.video_queue.set_state(state)?;
-        self.video_convert.set_state(state)?;
-        self.video_scale.set_state(state)?;
-        self.video_rate.set_state(state)?;
-        self.video_capsfilter.set_state(state)?;
-        self.video_encoder.set_state(state)?;
-        if let Some(encoder_parse) = &self.encoder_parse {
-            encoder_parse.set_state(state)?;
+        for element in self.elements() {
+            element.set_state(state)?;
+        }
+        Ok(())
+    }
+
+    pub fn recording(&self) -> bool {
+        self.recording
+    }
+
+    /// Starts a new recording after it was stopped, written to `location` or otherwise next to the
+    /// previous recording.
+    pub fn start_recording(&mut self, location: Option<&str>) -> Result<()> {
+        if self.recording {
+            return Err(MixerError::InvalidConfig(
+                "the output is already recording".to_string(),
+            ));
+        }
+
+        self.takes += 1;
+        self.location = match location {
+            Some(location) => location.to_string(),
+            None => numbered_location(&self.location, &format!("take{}", self.takes)),
+        };
+        if let Ok(mut segment_location) = self.segment_location.lock() {
+            *segment_location = self.location.clone();
+        }
+
+        // Elements which received an end of stream only accept data again after being reset.
+        for element in self.elements() {
+            element.set_state(gst::State::Null)?;
+        }
+        self.stopping.store(false, Ordering::SeqCst);
+        self.finished.store(false, Ordering::SeqCst);
+        for element in self.elements() {
+            element.sync_state_with_parent()?;
         }
-        self.mux_queue.set_state(state)?;
-        self.output_mux.set_state(state)?;
-        self.queue_sink.set_state(state)?;
-        self.video_sink.set_state(state)?;
-
-        self.audio_queue.set_state(state)?;
-        self.audio_convert.set_state(state)?;
-        self.audio_resample.set_state(state)?;
-        self.audioenc.set_state(state)?;
+
+        // Relinking makes the tees send their sticky events, such as caps, down again.
+        for (pad, probe) in self.gates.drain(..) {
+            if let Some(peer) = pad.get_peer() {
+                pad.unlink(&peer)?;
+                pad.link(&peer)
+                    .map_err(|e| MixerError::Gstreamer(format!("{:?}", e)))?;
+            }
+            pad.remove_probe(probe);
+        }
+        self.recording = true;
+
+        Ok(())
+    }
+
+    /// Stops the recording by ending the stream of this output only, so the file is finalized
+    /// while the program continues.
+    pub fn stop_recording(&mut self) -> Result<()> {
+        if !self.recording {
+            return Err(MixerError::InvalidConfig(
+                "the output is not recording".to_string(),
+            ));
+        }
+
+        self.stopping.store(true, Ordering::SeqCst);
+        for queue in [&self.video_queue, &self.audio_queue].iter() {
+            let sink = queue.get_static_pad("sink").ok_or_else(|| {
+                MixerError::Gstreamer("Failed to get sink pad of queue".to_string())
+            })?;
+            let tee_pad = sink.get_peer().ok_or_else(|| {
+                MixerError::Gstreamer("Failed to get peer pad of queue".to_string())
+            })?;
+            let probe = tee_pad
+                .add_probe(
+                    gst::PadProbeType::BUFFER | gst::PadProbeType::BUFFER_LIST,
+                    |_, _| gst::PadProbeReturn::Drop,
+                )
+                .ok_or_else(|| MixerError::Gstreamer("Failed to add pad probe".to_string()))?;
+            self.gates.push((tee_pad, probe));
+
+            sink.send_event(gst::event::Eos::new());
+        }
+        self.recording = false;
+
         Ok(())
     }
+
+    /// Closes the current file and continues the recording in a new one.
+    pub fn split(&self) -> Result<()> {
+        if !self.recording {
+            return Err(MixerError::InvalidConfig(
+                "the output is not recording".to_string(),
+            ));
+        }
+
+        self.splitmux.emit("split-now", &[])?;
+        Ok(())
+    }
+
+    /// Stops the recording and waits up to `timeout` for its file to be finalized, blocking the
+    /// calling thread. Returns whether it was.
+    pub fn finalize(&mut self, timeout: Duration) -> Result<bool> {
+        if self.recording {
+            self.stop_recording()?;
+        }
+
+        let deadline = Instant::now() + timeout;
+        while !self.finished.load(Ordering::SeqCst) {
+            if Instant::now() >= deadline {
+                return Ok(false);
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+
+        Ok(true)
+    }
+
+    fn elements(&self) -> Vec<&gst::Element> {
+        let mut elements = vec![
+            &self.video_queue,
+            &self.video_convert,
+            &self.video_scale,
+            &self.video_rate,
+            &self.video_capsfilter,
+            &self.video_encoder,
+        ];
+        if let Some(encoder_parse) = &self.encoder_parse {
+            elements.push(encoder_parse);
+        }
+        elements.extend(&[
+            &self.mux_queue,
+            &self.splitmux,
+            &self.audio_queue,
+            &self.audio_convert,
+            &self.audio_resample,
+            &self.audioenc,
+        ]);
+        elements
+    }
+}
+
+fn link_request_pad(src: &gst::Element, sink: &gst::Element, template: &str) -> Result<()> {
+    let src_pad = src
+        .get_static_pad("src")
+        .ok_or_else(|| MixerError::Gstreamer("Failed to get src pad".to_string()))?;
+    let sink_pad = sink.get_request_pad(template).ok_or_else(|| {
+        MixerError::Gstreamer(format!(
+            "Failed to request {} pad of splitmuxsink",
+            template
+        ))
+    })?;
+    src_pad
+        .link(&sink_pad)
+        .map_err(|e| MixerError::Gstreamer(format!("{:?}", e)))?;
+
+    Ok(())
+}
+
+/// Appends `suffix` to the file name of `location`, keeping its extension.
+fn numbered_location(location: &str, suffix: &str) -> String {
+    let path = std::path::Path::new(location);
+    match (path.file_stem(), path.extension()) {
+        (Some(stem), Some(extension)) => path
+            .with_file_name(format!(
+                "{}_{}.{}",
+                stem.to_string_lossy(),
+                suffix,
+                extension.to_string_lossy()
+            ))
+            .to_string_lossy()
+            .to_string(),
+        _ => format!("{}_{}", location, suffix),
+    }
 }
@@ -0,0 +1,273 @@
+use super::{Config, Output};
+use crate::mixer::{Error as MixerError, Tally};
+use crate::{gst_create_element, MultiviewerConfig, Result};
+use gst::prelude::*;
+use gstreamer as gst;
+use std::collections::HashMap;
+
+/// Composites the program and every input into a grid of labelled tiles, bordered by their tally,
+/// and sends it to another output.
+pub struct Multiviewer {
+    pub name: String,
+    pub sink: Box<Output>,
+    border: i32,
+    width: i32,
+    height: i32,
+    pipeline: Option<gst::Pipeline>,
+    compositor: gst::Element,
+    capsfilter: gst::Element,
+    tee: gst::Element,
+    program: Option<Tile>,
+    tiles: Vec<Tile>,
+}
+
+/// Scaled and labelled copy of a video tee, placed on the multiviewer compositor.
+struct Tile {
+    name: String,
+    source: gst::Element,
+    queue: gst::Element,
+    convert: gst::Element,
+    scale: gst::Element,
+    capsfilter: gst::Element,
+    overlay: gst::Element,
+    videobox: gst::Element,
+    pad: Option<gst::Pad>,
+}
+
+impl Multiviewer {
+    pub fn create(config: Config, sink: Output, multiviewer: &MultiviewerConfig) -> Result<Self> {
+        let name = config.name;
+        let compositor = gst_create_element("compositor", &format!("output_{}_compositor", name))?;
+        compositor.set_property_from_str("background", "black");
+        let capsfilter = gst_create_element("capsfilter", &format!("output_{}_capsfilter", name))?;
+        let caps = gst::Caps::builder("video/x-raw")
+            .field("framerate", &gst::Fraction::new(config.video.framerate, 1))
+            .field("width", &config.video.width)
+            .field("height", &config.video.height)
+            .build();
+        capsfilter.set_property("caps", &caps)?;
+        let tee = gst_create_element("tee", &format!("output_{}_tee", name))?;
+        tee.set_property("allow-not-linked", &true)?;
+
+        Ok(Self {
+            name,
+            sink: Box::new(sink),
+            border: multiviewer.border.max(0),
+            width: config.video.width,
+            height: config.video.height,
+            pipeline: None,
+            compositor,
+            capsfilter,
+            tee,
+            program: None,
+            tiles: vec![],
+        })
+    }
+
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    pub fn link(
+        &mut self,
+        pipeline: gst::Pipeline,
+        audio: gst::Element,
+        video: gst::Element,
+    ) -> Result<()> {
+        pipeline.add_many(&[&self.compositor, &self.capsfilter, &self.tee])?;
+        gst::Element::link_many(&[&self.compositor, &self.capsfilter, &self.tee])?;
+
+        let mut program = Tile::create(&self.name, "program", "Program", &video)?;
+        program.link(&pipeline, &self.compositor)?;
+        self.program = Some(program);
+        for tile in self.tiles.iter_mut() {
+            tile.link(&pipeline, &self.compositor)?;
+        }
+        self.sink.link(pipeline.clone(), audio, self.tee.clone())?;
+
+        self.pipeline = Some(pipeline);
+        self.arrange()
+    }
+
+    pub fn unlink(&self) -> Result<()> {
+        self.sink.unlink()?;
+
+        if let Some(pipeline) = self.pipeline.as_ref() {
+            for tile in self.tiles.iter().chain(self.program.iter()) {
+                tile.unlink(pipeline, &self.compositor)?;
+            }
+            pipeline.remove_many(&[&self.compositor, &self.capsfilter, &self.tee])?;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_state(&mut self, state: gst::State) -> Result<()> {
+        self.compositor.set_state(state)?;
+        self.capsfilter.set_state(state)?;
+        self.tee.set_state(state)?;
+        self.sink.set_state(state)
+    }
+
+    /// Adds a tile showing the video of an input, taken from its video tee.
+    pub fn add_input(&mut self, name: &str, video: &gst::Element) -> Result<()> {
+        if self.tiles.iter().any(|tile| tile.name == name) {
+            return Err(MixerError::Exists("tile".to_string(), name.to_string()));
+        }
+
+        let mut tile = Tile::create(&self.name, name, name, video)?;
+        if let Some(pipeline) = self.pipeline.as_ref() {
+            tile.link(pipeline, &self.compositor)?;
+        }
+        self.tiles.push(tile);
+
+        self.arrange()
+    }
+
+    pub fn remove_input(&mut self, name: &str) -> Result<()> {
+        let index = match self.tiles.iter().position(|tile| tile.name == name) {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+
+        let tile = self.tiles.remove(index);
+        if let Some(pipeline) = self.pipeline.as_ref() {
+            tile.unlink(pipeline, &self.compositor)?;
+        }
+
+        self.arrange()
+    }
+
+    /// Colors the border of each input tile by its tally, red when on air and green when visible.
+    pub fn set_tally(&self, tally: &HashMap<String, Tally>) {
+        for tile in self.tiles.iter() {
+            let fill = match tally.get(&tile.name) {
+                Some(Tally::OnAir) => "red",
+                Some(Tally::Visible) => "green",
+                _ => "black",
+            };
+            tile.videobox.set_property_from_str("fill", fill);
+        }
+    }
+
+    /// Lays the program and inputs out in the smallest grid holding all of them, program first.
+    fn arrange(&self) -> Result<()> {
+        let tiles: Vec<&Tile> = self.program.iter().chain(self.tiles.iter()).collect();
+        let count = tiles.len() as i32;
+        let columns = (f64::from(count).sqrt().ceil() as i32).max(1);
+        let rows = (count + columns - 1) / columns;
+        let width = self.width / columns;
+        let height = self.height / rows;
+
+        for (index, tile) in tiles.iter().enumerate() {
+            let index = index as i32;
+            tile.place(
+                (index % columns) * width,
+                (index / columns) * height,
+                width,
+                height,
+                self.border,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Tile {
+    fn create(output: &str, name: &str, label: &str, source: &gst::Element) -> Result<Self> {
+        let prefix = format!("output_{}_tile_{}", output, name);
+        let queue = gst_create_element("queue", &format!("{}_queue", prefix))?;
+        queue.set_property_from_str("leaky", "downstream");
+        let convert = gst_create_element("videoconvert", &format!("{}_convert", prefix))?;
+        let scale = gst_create_element("videoscale", &format!("{}_scale", prefix))?;
+        let capsfilter = gst_create_element("capsfilter", &format!("{}_capsfilter", prefix))?;
+        let overlay = gst_create_element("textoverlay", &format!("{}_overlay", prefix))?;
+        overlay.set_property("text", &label)?;
+        overlay.set_property("shaded-background", &true)?;
+        overlay.set_property_from_str("valignment", "bottom");
+        overlay.set_property_from_str("halignment", "center");
+        let videobox = gst_create_element("videobox", &format!("{}_videobox", prefix))?;
+        videobox.set_property_from_str("fill", "black");
+
+        Ok(Self {
+            name: name.to_string(),
+            source: source.clone(),
+            queue,
+            convert,
+            scale,
+            capsfilter,
+            overlay,
+            videobox,
+            pad: None,
+        })
+    }
+
+    fn elements(&self) -> [&gst::Element; 6] {
+        [
+            &self.queue,
+            &self.convert,
+            &self.scale,
+            &self.capsfilter,
+            &self.overlay,
+            &self.videobox,
+        ]
+    }
+
+    fn link(&mut self, pipeline: &gst::Pipeline, compositor: &gst::Element) -> Result<()> {
+        pipeline.add_many(&self.elements())?;
+        gst::Element::link_many(&self.elements())?;
+        self.source.link(&self.queue)?;
+
+        let pad = compositor.get_request_pad("sink_%u").ok_or_else(|| {
+            MixerError::Gstreamer("Failed to request pad of compositor".to_string())
+        })?;
+        self.videobox
+            .get_static_pad("src")
+            .ok_or_else(|| MixerError::Gstreamer("Failed to get src pad of tile".to_string()))?
+            .link(&pad)
+            .map_err(|e| MixerError::Gstreamer(format!("{:?}", e)))?;
+        self.pad = Some(pad);
+
+        for element in self.elements().iter() {
+            element.sync_state_with_parent()?;
+        }
+
+        Ok(())
+    }
+
+    fn unlink(&self, pipeline: &gst::Pipeline, compositor: &gst::Element) -> Result<()> {
+        super::release_request_pad(&self.queue)?;
+        if let Some(pad) = self.pad.as_ref() {
+            compositor.release_request_pad(pad);
+        }
+
+        let elements = self.elements();
+        for element in elements.iter() {
+            element.set_state(gst::State::Null)?;
+        }
+        pipeline.remove_many(&elements)?;
+
+        Ok(())
+    }
+
+    /// Places the tile, scaling its video to fit inside the border.
+    fn place(&self, xpos: i32, ypos: i32, width: i32, height: i32, border: i32) -> Result<()> {
+        let caps = gst::Caps::builder("video/x-raw")
+            .field("width", &(width - 2 * border).max(1))
+            .field("height", &(height - 2 * border).max(1))
+            .field("pixel-aspect-ratio", &gst::Fraction::new(1, 1))
+            .build();
+        self.capsfilter.set_property("caps", &caps)?;
+        for side in ["left", "right", "top", "bottom"].iter() {
+            self.videobox.set_property(*side, &-border)?;
+        }
+
+        if let Some(pad) = self.pad.as_ref() {
+            pad.set_property("xpos", &xpos)?;
+            pad.set_property("ypos", &ypos)?;
+        }
+
+        Ok(())
+    }
+}
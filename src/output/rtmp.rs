@@ -43,6 +43,9 @@ impl RTMP {
         let video_caps = gst::Caps::builder("video/x-raw")
             .field("framerate", &gst::Fraction::new(config.video.framerate, 1))
             .field("format", &config.video.format.to_string())
+            .field("width", &config.video.width)
+            .field("height", &config.video.height)
+            .field("pixel-aspect-ratio", &gst::Fraction::new(1, 1))
             .field(
                 "profile",
                 &config
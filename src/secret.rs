@@ -0,0 +1,165 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// Shown in place of a secret.
+const REDACTED: &str = "***";
+
+/// Parts of query parameter and option names whose values are secret, such as `key`, `streamkey`,
+/// `token`, `passphrase` or `pubPasswd`.
+const SECRET_PARAMS: [&str; 6] = ["key", "token", "pass", "secret", "auth", "sig"];
+
+/// Query parameters whose values are secret as a whole, such as SRT stream ids carrying keys.
+const SECRET_EXACT_PARAMS: [&str; 1] = ["streamid"];
+
+/// Schemes whose path holds a stream key after the application name.
+const STREAM_KEY_SCHEMES: [&str; 4] = ["rtmp", "rtmps", "rtmpt", "rtmpe"];
+
+/// URL which may hold secrets, such as RTMP stream keys, passwords and tokens. Its `Debug`,
+/// `Display` and `Serialize` output redacts them, so it can be logged and returned to clients.
+/// Use [`reveal`](#method.reveal) where the full URL is needed.
+#[derive(Clone, Default, PartialEq)]
+pub struct SecretUrl(String);
+
+impl SecretUrl {
+    pub fn new(url: impl Into<String>) -> Self {
+        SecretUrl(url.into())
+    }
+
+    /// Returns the URL including its secrets.
+    pub fn reveal(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns the URL with the password, stream key and secret query parameters replaced.
+    /// Options following the URL after a space, as accepted by `rtmpsink`, are redacted too, as is
+    /// every URL of a comma separated list.
+    pub fn redacted(&self) -> String {
+        let mut urls: Vec<String> = vec![];
+        for part in self.0.split(',') {
+            match urls.last_mut() {
+                // Commas within a URL, such as in its query, don't start another one
+                Some(url) if !part.contains("://") => {
+                    url.push(',');
+                    url.push_str(part);
+                }
+                _ => urls.push(part.to_string()),
+            }
+        }
+
+        urls.iter()
+            .map(|url| {
+                let mut parts = url.split(' ');
+                let mut redacted = redact_url(parts.next().unwrap_or_default());
+                for option in parts {
+                    redacted.push(' ');
+                    redacted.push_str(&redact_param(option));
+                }
+                redacted
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Returns whether the URL holds the marker shown in place of secrets, as it does when a
+    /// redacted URL is sent back by a client.
+    pub fn is_redacted(&self) -> bool {
+        self.0.contains(REDACTED)
+    }
+}
+
+impl From<String> for SecretUrl {
+    fn from(url: String) -> Self {
+        SecretUrl(url)
+    }
+}
+
+impl From<&str> for SecretUrl {
+    fn from(url: &str) -> Self {
+        SecretUrl(url.to_string())
+    }
+}
+
+impl fmt::Debug for SecretUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.redacted())
+    }
+}
+
+impl fmt::Display for SecretUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.redacted())
+    }
+}
+
+impl Serialize for SecretUrl {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.redacted())
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretUrl {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(SecretUrl)
+    }
+}
+
+fn redact_url(url: &str) -> String {
+    let (scheme, rest) = match url.find("://") {
+        Some(index) => (&url[..index], &url[index + 3..]),
+        // Paths of local files
+        None => return url.to_string(),
+    };
+    let (rest, query) = match rest.find('?') {
+        Some(index) => (&rest[..index], Some(&rest[index + 1..])),
+        None => (rest, None),
+    };
+    let (authority, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, ""),
+    };
+
+    let mut redacted = format!("{}://", scheme);
+    match authority.rfind('@') {
+        Some(index) => {
+            // The host keeps the `@` separating it from the user
+            let (userinfo, host) = authority.split_at(index);
+            match userinfo.find(':') {
+                Some(colon) => redacted.push_str(&format!("{}:{}", &userinfo[..colon], REDACTED)),
+                None => redacted.push_str(userinfo),
+            }
+            redacted.push_str(host);
+        }
+        None => redacted.push_str(authority),
+    }
+
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    if STREAM_KEY_SCHEMES.contains(&scheme.to_lowercase().as_str()) && segments.len() > 1 {
+        redacted.push_str(&format!("/{}/{}", segments[0], REDACTED));
+    } else {
+        redacted.push_str(path);
+    }
+
+    if let Some(query) = query {
+        let params: Vec<String> = query.split('&').map(redact_param).collect();
+        redacted.push('?');
+        redacted.push_str(&params.join("&"));
+    }
+
+    redacted
+}
+
+/// Redacts the value of a `name=value` pair when its name looks like it holds a secret.
+fn redact_param(param: &str) -> String {
+    let index = match param.find('=') {
+        Some(index) => index,
+        None => return param.to_string(),
+    };
+    let name = param[..index].to_lowercase();
+    if SECRET_PARAMS.iter().any(|secret| name.contains(secret))
+        || SECRET_EXACT_PARAMS.contains(&name.as_str())
+    {
+        format!("{}={}", &param[..index], REDACTED)
+    } else {
+        param.to_string()
+    }
+}
@@ -0,0 +1,144 @@
+use crate::{gst_create_element, mixer::Error, Result};
+use gst::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+/// Frames per second kept for snapshots, anything more is dropped before it is converted.
+const SNAPSHOT_RATE: i32 = 2;
+
+/// Largest width or height a snapshot may be scaled to.
+const MAX_SIZE: i32 = 3840;
+
+/// Encoding of a snapshot.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    JPEG,
+    PNG,
+}
+
+impl ImageFormat {
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            ImageFormat::JPEG => "image/jpeg",
+            ImageFormat::PNG => "image/png",
+        }
+    }
+}
+
+/// Branch off a video tee holding on to its latest frame, which is encoded on request. Only a few
+/// frames per second enter the branch and it never blocks the tee.
+pub struct Snapshot {
+    queue: gst::Element,
+    rate: gst::Element,
+    sink: gst::Element,
+    sample: Arc<Mutex<Option<gst::Sample>>>,
+}
+
+impl Snapshot {
+    pub fn create(name: &str) -> Result<Self> {
+        let queue = gst_create_element("queue", &format!("{}_snapshot_queue", name))?;
+        queue.set_property_from_str("leaky", "downstream");
+        queue.set_property("max-size-buffers", &1u32)?;
+        queue.set_property("max-size-bytes", &0u32)?;
+        queue.set_property("max-size-time", &0u64)?;
+        let rate = gst_create_element("videorate", &format!("{}_snapshot_rate", name))?;
+        rate.set_property("drop-only", &true)?;
+        rate.set_property("max-rate", &SNAPSHOT_RATE)?;
+        let sink = gst_create_element("appsink", &format!("{}_snapshot_appsink", name))?;
+        sink.set_property("sync", &false)?;
+        sink.set_property("async", &false)?;
+        sink.set_property("max-buffers", &1u32)?;
+        sink.set_property("drop", &true)?;
+        sink.set_property("emit-signals", &true)?;
+
+        let sample = Arc::new(Mutex::new(None));
+        let latest = sample.clone();
+        sink.connect("new-sample", false, move |args| {
+            let pulled = args[0]
+                .get::<gst::Element>()
+                .ok()
+                .flatten()
+                .and_then(|sink| sink.emit("pull-sample", &[]).ok().flatten())
+                .and_then(|sample| sample.get::<gst::Sample>().ok().flatten());
+            if let (Some(pulled), Ok(mut sample)) = (pulled, latest.lock()) {
+                *sample = Some(pulled);
+            }
+            Some(gst::FlowReturn::Ok.to_value())
+        })?;
+
+        Ok(Self {
+            queue,
+            rate,
+            sink,
+            sample,
+        })
+    }
+
+    pub fn link(&self, pipeline: &gst::Pipeline, tee: &gst::Element) -> Result<()> {
+        pipeline.add_many(&[&self.queue, &self.rate, &self.sink])?;
+        gst::Element::link_many(&[tee, &self.queue, &self.rate, &self.sink])?;
+        Ok(())
+    }
+
+    pub fn unlink(&self, pipeline: &gst::Pipeline) -> Result<()> {
+        let pad = self
+            .queue
+            .get_static_pad("sink")
+            .ok_or_else(|| Error::Gstreamer("Failed to get sink pad of queue".to_string()))?;
+        if let Some(tee_pad) = pad.get_peer() {
+            if let Some(tee) = tee_pad.get_parent_element() {
+                tee.release_request_pad(&tee_pad);
+            }
+        }
+
+        pipeline.remove_many(&[&self.queue, &self.rate, &self.sink])?;
+        Ok(())
+    }
+
+    pub fn set_state(&self, state: gst::State) -> Result<()> {
+        self.queue.set_state(state)?;
+        self.rate.set_state(state)?;
+        self.sink.set_state(state)?;
+        Ok(())
+    }
+
+    /// Returns the latest frame, to be encoded with [`encode`](fn.encode.html).
+    pub fn latest(&self) -> Result<gst::Sample> {
+        self.sample
+            .lock()
+            .map_err(|e| Error::System(e.to_string()))?
+            .clone()
+            .ok_or_else(|| Error::NotFound("snapshot".to_string(), "latest".to_string()))
+    }
+}
+
+/// Encodes a frame, scaled to the given size. When only one dimension is given the other keeps the
+/// aspect ratio of the frame. Converting may block for up to a second.
+pub fn encode(
+    sample: &gst::Sample,
+    format: ImageFormat,
+    width: Option<i32>,
+    height: Option<i32>,
+) -> Result<Vec<u8>> {
+    let mut caps = gst::Caps::builder(format.mime_type());
+    for (field, size) in [("width", width), ("height", height)].iter() {
+        if let Some(size) = size {
+            if *size < 1 || *size > MAX_SIZE {
+                return Err(Error::InvalidConfig(format!(
+                    "{} must be between 1 and {}",
+                    field, MAX_SIZE
+                )));
+            }
+            caps = caps.field(field, size);
+        }
+    }
+
+    let image = gst_video::convert_sample(sample, &caps.build(), gst::ClockTime::from_seconds(1))
+        .map_err(|e| Error::Gstreamer(e.to_string()))?;
+    let buffer = image
+        .get_buffer()
+        .ok_or_else(|| Error::Gstreamer("Snapshot has no buffer".to_string()))?;
+    let map = buffer.map_readable()?;
+
+    Ok(map.as_slice().to_vec())
+}